@@ -1,9 +1,5 @@
 use bytemuck::{Pod, Zeroable};
 use wgpu::{util::DeviceExt, Device};
-use winit::{
-    dpi::PhysicalSize,
-    event::{MouseScrollDelta, WindowEvent},
-};
 
 #[derive(Default)]
 pub(crate) struct Camera {
@@ -54,42 +50,3 @@ impl CameraUniform {
         }
     }
 }
-
-#[derive(Default, Debug)]
-pub(crate) struct CameraController {
-    pub(crate) speed: f32,
-    pub(crate) delta_x: f32,
-    pub(crate) delta_y: f32,
-}
-
-impl CameraController {
-    pub(crate) fn new(speed: f32) -> Self {
-        Self {
-            speed,
-            ..Default::default()
-        }
-    }
-
-    pub(crate) fn process_event(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::MouseWheel {
-                delta: MouseScrollDelta::LineDelta(x, y),
-                ..
-            } => {
-                self.delta_x += *x;
-                self.delta_y += *y;
-                true
-            }
-            _ => false,
-        }
-    }
-
-    pub(crate) fn update_camera(&mut self, camera: &mut Camera, size: PhysicalSize<u32>) {
-        camera.x += self.delta_x.powi(2) * self.speed * self.delta_x.signum();
-        camera.y += self.delta_y.powi(2) * self.speed * self.delta_y.signum();
-        camera.width = size.width as f32;
-        camera.height = size.height as f32;
-        self.delta_x = 0.0;
-        self.delta_y = 0.0;
-    }
-}
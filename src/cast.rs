@@ -0,0 +1,87 @@
+use datafusion::{
+    arrow::datatypes::{DataType, TimeUnit},
+    error::Result,
+    prelude::*,
+};
+
+use crate::settings;
+
+pub(crate) const CAST_TYPES: [(&str, DataType); 6] = [
+    ("Utf8", DataType::Utf8),
+    ("Int64", DataType::Int64),
+    ("Float64", DataType::Float64),
+    ("Boolean", DataType::Boolean),
+    ("Date32", DataType::Date32),
+    (
+        "Timestamp",
+        DataType::Timestamp(TimeUnit::Microsecond, None),
+    ),
+];
+
+const STORE: &str = "casts.json";
+
+// values that don't parse become null instead of failing the whole query
+pub(crate) fn apply(df: DataFrame, casts: &[(String, DataType)]) -> Result<DataFrame> {
+    if casts.is_empty() {
+        return Ok(df);
+    }
+    let exprs: Vec<_> = df
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| match casts.iter().find(|(name, _)| name == f.name()) {
+            Some((name, data_type)) => {
+                try_cast(ident(name), data_type.clone()).alias(name.as_str())
+            }
+            None => ident(f.name()),
+        })
+        .collect();
+    df.select(exprs)
+}
+
+pub(crate) fn columns(casts: &[(String, DataType)]) -> Vec<String> {
+    casts.iter().map(|(name, _)| name.clone()).collect()
+}
+
+pub(crate) async fn failures(df: DataFrame, column: &str, data_type: &DataType) -> Result<usize> {
+    df.filter(
+        ident(column)
+            .is_not_null()
+            .and(try_cast(ident(column), data_type.clone()).is_null()),
+    )?
+    .count()
+    .await
+}
+
+pub(crate) fn load(path: &str) -> Vec<(String, DataType)> {
+    settings::read(path, STORE).map_or(vec![], |casts| from_json(&casts))
+}
+
+pub(crate) fn save(path: &str, casts: &[(String, DataType)]) {
+    let casts = to_json(casts);
+    settings::write(path, STORE, (!casts.is_empty()).then(|| casts.into()));
+}
+
+// each column's type by its name in `CAST_TYPES`, as the store and view files keep them
+pub(crate) fn to_json(casts: &[(String, DataType)]) -> serde_json::Map<String, serde_json::Value> {
+    casts
+        .iter()
+        .filter_map(|(column, data_type)| {
+            let (name, _) = CAST_TYPES.iter().find(|(_, t)| t == data_type)?;
+            Some((column.clone(), serde_json::Value::from(*name)))
+        })
+        .collect()
+}
+
+pub(crate) fn from_json(casts: &serde_json::Value) -> Vec<(String, DataType)> {
+    let Some(casts) = casts.as_object() else {
+        return vec![];
+    };
+    casts
+        .iter()
+        .filter_map(|(column, name)| {
+            let (_, data_type) = CAST_TYPES.iter().find(|(n, _)| Some(*n) == name.as_str())?;
+            Some((column.clone(), data_type.clone()))
+        })
+        .collect()
+}
@@ -0,0 +1,55 @@
+use std::{ops::Range, path::PathBuf};
+
+use clap::{Parser, Subcommand};
+
+use crate::source::FileOptions;
+
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub(crate) struct Cli {
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+    /// File to open
+    #[arg(default_value = "measurements.csv")]
+    pub(crate) path: String,
+    /// Draw the grid in the terminal instead of a window
+    #[arg(long)]
+    pub(crate) tui: bool,
+    /// Keep reading a CSV or NDJSON file as it grows
+    #[arg(long)]
+    pub(crate) follow: bool,
+    #[command(flatten)]
+    pub(crate) options: FileOptions,
+}
+
+#[derive(Subcommand)]
+pub(crate) enum Command {
+    /// Render rows of a file to a PNG without opening a window
+    Render {
+        /// File to render
+        path: String,
+        /// Rows to render, as start..end
+        #[arg(long, default_value = "0..50", value_parser = parse_range)]
+        rows: Range<usize>,
+        /// Where to write the PNG
+        #[arg(short, long, default_value = "out.png")]
+        output: PathBuf,
+        /// Image width in pixels, wide enough for every column by default
+        #[arg(long)]
+        width: Option<u32>,
+        #[command(flatten)]
+        options: FileOptions,
+    },
+}
+
+fn parse_range(s: &str) -> Result<Range<usize>, String> {
+    let (start, end) = s
+        .split_once("..")
+        .ok_or_else(|| format!("expected start..end, got {s}"))?;
+    let start = start.parse().map_err(|e| format!("bad range start: {e}"))?;
+    let end = end.parse().map_err(|e| format!("bad range end: {e}"))?;
+    if end <= start {
+        return Err(format!("empty range {s}"));
+    }
+    Ok(start..end)
+}
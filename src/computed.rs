@@ -0,0 +1,216 @@
+use std::sync::Arc;
+
+use datafusion::{
+    arrow::datatypes::DataType,
+    common::plan_err,
+    config::ConfigOptions,
+    error::{DataFusionError, Result},
+    execution::context::SessionState,
+    logical_expr::{AggregateUDF, ScalarUDF, TableSource, WindowUDF},
+    prelude::*,
+    sql::{
+        planner::{ContextProvider, PlannerContext, SqlToRel},
+        sqlparser::{dialect::GenericDialect, parser::Parser},
+        TableReference,
+    },
+};
+
+use crate::settings;
+
+// a column added to the view, by name and the sql expression it is computed with
+pub(crate) type Computed = (String, String);
+
+const STORE: &str = "columns.json";
+
+// `outlet - inlet as delta` names the column, otherwise it is named after the expression
+pub(crate) fn parse(text: &str) -> Computed {
+    let text = text.trim();
+    // ascii only, so the offset is one into the text
+    if let Some(i) = text.to_ascii_lowercase().rfind(" as ") {
+        let name = text[i + 4..].trim();
+        if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return (name.to_string(), text[..i].trim().to_string());
+        }
+    }
+    (text.to_string(), text.to_string())
+}
+
+// the columns are appended in the order they were added, each can use the ones before it.
+// A type override of a computed column is applied to the computed values
+pub(crate) fn apply(
+    state: &SessionState,
+    mut df: DataFrame,
+    computed: &[Computed],
+    casts: &[(String, DataType)],
+) -> Result<DataFrame> {
+    for (name, expression) in computed {
+        let mut expr = plan(state, &df, expression)?;
+        if let Some((_, data_type)) = casts.iter().find(|(n, _)| n == name) {
+            expr = try_cast(expr, data_type.clone());
+        }
+        df = df.with_column(name, expr)?;
+    }
+    Ok(df)
+}
+
+// a new column is planned on its own first, so a mistake in it is reported before it's kept
+pub(crate) fn check(state: &SessionState, df: &DataFrame, column: &Computed) -> Result<()> {
+    let (name, expression) = column;
+    if df.schema().fields().iter().any(|f| f.name() == name) {
+        return plan_err!("there is already a column named {name}");
+    }
+    let expr = plan(state, df, expression)?;
+    df.clone().with_column(name, expr)?;
+    Ok(())
+}
+
+fn plan(state: &SessionState, df: &DataFrame, expression: &str) -> Result<Expr> {
+    let sql = Parser::new(&GenericDialect {})
+        .try_with_sql(expression)?
+        .parse_expr()?;
+    SqlToRel::new(&Functions(state)).sql_to_expr(sql, df.schema(), &mut PlannerContext::new())
+}
+
+pub(crate) fn load(path: &str) -> Vec<Computed> {
+    settings::read(path, STORE).map_or(vec![], |columns| from_json(&columns))
+}
+
+pub(crate) fn save(path: &str, computed: &[Computed]) {
+    let columns = to_json(computed);
+    settings::write(path, STORE, (!columns.is_empty()).then(|| columns.into()));
+}
+
+pub(crate) fn to_json(computed: &[Computed]) -> Vec<serde_json::Value> {
+    computed
+        .iter()
+        .map(|(name, expression)| serde_json::json!({"name": name, "expression": expression}))
+        .collect()
+}
+
+pub(crate) fn from_json(columns: &serde_json::Value) -> Vec<Computed> {
+    let Some(columns) = columns.as_array() else {
+        return vec![];
+    };
+    columns
+        .iter()
+        .filter_map(|column| {
+            let name = column.get("name")?.as_str()?;
+            let expression = column.get("expression")?.as_str()?;
+            Some((name.to_string(), expression.to_string()))
+        })
+        .collect()
+}
+
+// an expression refers to no tables, only to the functions registered with the context
+struct Functions<'a>(&'a SessionState);
+
+impl ContextProvider for Functions<'_> {
+    fn get_table_source(&self, name: TableReference) -> Result<Arc<dyn TableSource>> {
+        plan_err!("an expression can't refer to table {name}")
+    }
+
+    fn get_function_meta(&self, name: &str) -> Option<Arc<ScalarUDF>> {
+        self.0.scalar_functions().get(name).cloned()
+    }
+
+    fn get_aggregate_meta(&self, name: &str) -> Option<Arc<AggregateUDF>> {
+        self.0.aggregate_functions().get(name).cloned()
+    }
+
+    fn get_window_meta(&self, name: &str) -> Option<Arc<WindowUDF>> {
+        self.0.window_functions().get(name).cloned()
+    }
+
+    fn get_variable_type(&self, _variable_names: &[String]) -> Option<DataType> {
+        None
+    }
+
+    fn options(&self) -> &ConfigOptions {
+        self.0.config_options()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::{
+        array::{ArrayRef, Int64Array, RecordBatch},
+        util::pretty::pretty_format_batches,
+    };
+
+    use super::*;
+
+    fn df(ctx: &SessionContext) -> DataFrame {
+        let inlet = Arc::new(Int64Array::from(vec![1, 5])) as ArrayRef;
+        let outlet = Arc::new(Int64Array::from(vec![4, 6])) as ArrayRef;
+        let batch = RecordBatch::try_from_iter([("inlet", inlet), ("outlet", outlet)]).unwrap();
+        ctx.read_batch(batch).unwrap()
+    }
+
+    #[test]
+    fn columns_are_named_after_as_or_the_expression() {
+        let named = |name: &str, expression: &str| (name.to_string(), expression.to_string());
+        assert_eq!(
+            parse(" outlet - inlet as delta "),
+            named("delta", "outlet - inlet")
+        );
+        assert_eq!(
+            parse("outlet - inlet AS delta"),
+            named("delta", "outlet - inlet")
+        );
+        assert_eq!(parse("cast(a as int) as b"), named("b", "cast(a as int)"));
+        // only a plain name after the last `as` names the column
+        assert_eq!(
+            parse("cast(a as int)"),
+            named("cast(a as int)", "cast(a as int)")
+        );
+        assert_eq!(
+            parse("a as two words"),
+            named("a as two words", "a as two words")
+        );
+        assert_eq!(parse("a as "), named("a as", "a as"));
+    }
+
+    #[tokio::test]
+    async fn columns_use_the_ones_before_them() {
+        let ctx = SessionContext::new();
+        let computed = [
+            parse("outlet - inlet as delta"),
+            parse("delta * 2 as twice"),
+        ];
+        let casts = [("twice".to_string(), DataType::Utf8)];
+        let df = apply(&ctx.state(), df(&ctx), &computed, &casts).unwrap();
+        let schema = df.schema().clone();
+        let batches = df.collect().await.unwrap();
+        assert_eq!(schema.field(3).data_type(), &DataType::Utf8);
+        assert_eq!(
+            pretty_format_batches(&batches).unwrap().to_string(),
+            "+-------+--------+-------+-------+\n\
+             | inlet | outlet | delta | twice |\n\
+             +-------+--------+-------+-------+\n\
+             | 1     | 4      | 3     | 6     |\n\
+             | 5     | 6      | 1     | 2     |\n\
+             +-------+--------+-------+-------+"
+        );
+    }
+
+    #[test]
+    fn a_mistake_is_told_before_the_column_is_kept() {
+        let ctx = SessionContext::new();
+        let (state, df) = (ctx.state(), df(&ctx));
+        assert!(check(&state, &df, &parse("outlet - inlet as delta")).is_ok());
+        let e = check(&state, &df, &parse("outlet as inlet")).unwrap_err();
+        assert!(
+            e.to_string().contains("already a column named inlet"),
+            "{e}"
+        );
+        assert!(check(&state, &df, &parse("missing + 1")).is_err());
+        assert!(check(&state, &df, &parse("outlet +")).is_err());
+    }
+
+    #[test]
+    fn columns_read_back_as_they_were_written() {
+        let computed = vec![parse("outlet - inlet as delta"), parse("inlet + 1")];
+        assert_eq!(from_json(&to_json(&computed).into()), computed);
+        assert!(from_json(&serde_json::json!({"name": "x"})).is_empty());
+    }
+}
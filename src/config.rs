@@ -0,0 +1,391 @@
+use std::{
+    fmt, fs,
+    path::PathBuf,
+    sync::{Arc, OnceLock, RwLock},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use serde_json::Value;
+
+use crate::{
+    theme::Theme,
+    viewport::{COL_WIDTH, PAGE_SIZE, ROW_HEIGHT},
+    worker::Notify,
+};
+
+// how often the file is checked for being edited
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// what the window does on a key, the ones left out keep their usual key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Action {
+    Edit,
+    AddColumn,
+    Undo,
+    Redo,
+    SaveEdits,
+    ExportSql,
+    Group,
+    Drill,
+    Promote,
+    Hide,
+    ShowAll,
+    Pin,
+    MoveLeft,
+    MoveRight,
+    SaveView,
+}
+
+const ACTIONS: [(&str, Action, &[&str]); 15] = [
+    ("edit", Action::Edit, &["e"]),
+    ("add_column", Action::AddColumn, &["a"]),
+    ("undo", Action::Undo, &["ctrl+z"]),
+    ("redo", Action::Redo, &["ctrl+Z", "ctrl+y"]),
+    ("save_edits", Action::SaveEdits, &["ctrl+s"]),
+    ("export_sql", Action::ExportSql, &["ctrl+e"]),
+    ("group", Action::Group, &["b"]),
+    ("drill", Action::Drill, &["d"]),
+    ("promote", Action::Promote, &["p"]),
+    ("hide", Action::Hide, &["x"]),
+    ("show_all", Action::ShowAll, &["X"]),
+    ("pin", Action::Pin, &["f"]),
+    ("move_left", Action::MoveLeft, &["<"]),
+    ("move_right", Action::MoveRight, &[">"]),
+    ("save_view", Action::SaveView, &["v"]),
+];
+
+// a key with ctrl held or not, as winit tells the character
+#[derive(Debug, Clone, PartialEq)]
+struct Binding {
+    ctrl: bool,
+    key: String,
+}
+
+impl Binding {
+    fn parse(text: &str) -> Option<Self> {
+        let (ctrl, key) = match text.strip_prefix("ctrl+") {
+            Some(key) => (true, key),
+            None => (false, text),
+        };
+        (key.chars().count() == 1).then(|| Self {
+            ctrl,
+            key: key.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.ctrl {
+            true => write!(f, "ctrl+{}", self.key),
+            false => write!(f, "{}", self.key),
+        }
+    }
+}
+
+// how the window looks and behaves, read from `config.json` in the config directory
+#[derive(Debug, Clone)]
+pub(crate) struct Config {
+    pub(crate) theme: Theme,
+    pub(crate) font_size: f32,
+    pub(crate) row_height: f32,
+    pub(crate) column_width: f32,
+    pub(crate) scroll_speed: f32,
+    // rows fetched at a time, more than a screenful
+    pub(crate) page_size: usize,
+    keys: Vec<(Binding, Action)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let keys = ACTIONS
+            .iter()
+            .flat_map(|(_, action, keys)| keys.iter().map(move |k| (k, *action)))
+            .filter_map(|(key, action)| Some((Binding::parse(key)?, action)))
+            .collect();
+        Self {
+            theme: Theme::default(),
+            font_size: 12.,
+            row_height: ROW_HEIGHT,
+            column_width: COL_WIDTH,
+            scroll_speed: 10.,
+            page_size: PAGE_SIZE,
+            keys,
+        }
+    }
+}
+
+impl Config {
+    pub(crate) fn action(&self, key: &str, ctrl: bool) -> Option<Action> {
+        self.keys
+            .iter()
+            .find(|(binding, _)| binding.ctrl == ctrl && binding.key == key)
+            .map(|(_, action)| *action)
+    }
+
+    // the first key an action is bound to, for telling about it. An unbound action has none
+    pub(crate) fn key(&self, action: Action) -> Option<String> {
+        self.keys
+            .iter()
+            .find(|(_, a)| *a == action)
+            .map(|(binding, _)| binding.to_string())
+    }
+
+    // what the file leaves out stays as it was by default
+    fn from_json(value: &Value) -> Result<Self, String> {
+        let mut config = Self::default();
+        let number = |key: &str, min: f64| -> Result<Option<f64>, String> {
+            let Some(value) = value.get(key) else {
+                return Ok(None);
+            };
+            match value.as_f64() {
+                Some(n) if n >= min => Ok(Some(n)),
+                _ => Err(format!("{key} should be a number of at least {min}")),
+            }
+        };
+        if let Some(n) = number("font_size", 1.)? {
+            config.font_size = n as f32;
+        }
+        if let Some(n) = number("row_height", 1.)? {
+            config.row_height = n as f32;
+        }
+        if let Some(n) = number("column_width", 20.)? {
+            config.column_width = n as f32;
+        }
+        if let Some(n) = number("scroll_speed", 0.)? {
+            config.scroll_speed = n as f32;
+        }
+        if let Some(n) = number("page_size", 1.)? {
+            config.page_size = n as usize;
+        }
+        let name = |key: &str| -> Result<Option<&str>, String> {
+            value.get(key).map_or(Ok(None), |value| {
+                value
+                    .as_str()
+                    .map(Some)
+                    .ok_or(format!("{key} should be a name"))
+            })
+        };
+        if let Some(name) = name("theme")? {
+            config.theme = Theme::named(name).ok_or(format!("there is no theme called {name}"))?;
+        }
+        if let Some(name) = name("highlights")? {
+            config.theme.highlights =
+                Theme::palette(name).ok_or(format!("there are no highlights called {name}"))?;
+        }
+        // single colors go on top of the theme
+        let theme = &mut config.theme;
+        let colors = value.get("colors").unwrap_or(&Value::Null);
+        let color = |key: &str| -> Result<Option<[u8; 3]>, String> {
+            colors.get(key).map_or(Ok(None), |value| {
+                value
+                    .as_str()
+                    .and_then(parse_color)
+                    .map(Some)
+                    .ok_or(format!("colors.{key} should look like \"#rrggbb\""))
+            })
+        };
+        let slots = [
+            ("background", &mut theme.background),
+            ("tab", &mut theme.tab_text),
+            ("header", &mut theme.header_text),
+            ("cell", &mut theme.cell_text),
+            ("panel", &mut theme.panel_text),
+            ("plot", &mut theme.highlights.plot),
+            ("selection", &mut theme.highlights.selection),
+            ("edited", &mut theme.highlights.edited),
+        ];
+        for (key, slot) in slots {
+            if let Some(rgb) = color(key)? {
+                *slot = rgb;
+            }
+        }
+        if let Some(keys) = value.get("keys").and_then(Value::as_object) {
+            // bound keys go first, so they win over a default that uses the same key
+            let mut bound = vec![];
+            for (name, keys) in keys {
+                let Some((_, action, _)) = ACTIONS.iter().find(|(n, ..)| n == name) else {
+                    return Err(format!("there is no action called {name}"));
+                };
+                let keys = match keys {
+                    Value::Array(keys) => keys.iter().collect(),
+                    key => vec![key],
+                };
+                for key in keys {
+                    let binding = key.as_str().and_then(Binding::parse).ok_or(format!(
+                        "keys.{name} should be a key like \"e\" or \"ctrl+e\""
+                    ))?;
+                    bound.push((binding, *action));
+                }
+                config.keys.retain(|(_, a)| a != action);
+            }
+            bound.append(&mut config.keys);
+            config.keys = bound;
+        }
+        Ok(config)
+    }
+}
+
+fn parse_color(text: &str) -> Option<[u8; 3]> {
+    let hex = text.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn file() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join("raam").join("config.json"))
+}
+
+// no file is the default config, a broken one is told about
+fn load() -> Result<Config, String> {
+    let Some(file) = file().filter(|file| file.is_file()) else {
+        return Ok(Config::default());
+    };
+    let text =
+        fs::read_to_string(&file).map_err(|e| format!("could not read {}: {e}", file.display()))?;
+    let value: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("{} is not valid json: {e}", file.display()))?;
+    Config::from_json(&value).map_err(|e| format!("{}: {e}", file.display()))
+}
+
+fn current() -> &'static RwLock<Arc<Config>> {
+    static CURRENT: OnceLock<RwLock<Arc<Config>>> = OnceLock::new();
+    CURRENT.get_or_init(|| {
+        let config = load().unwrap_or_else(|e| {
+            log::error!("{e}, using the default config");
+            Config::default()
+        });
+        RwLock::new(Arc::new(config))
+    })
+}
+
+// the config as last read, a new one once the file was edited
+pub(crate) fn get() -> Arc<Config> {
+    current().read().unwrap().clone()
+}
+
+fn stamp(file: &PathBuf) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(file).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// rereads the file whenever it is edited, and wakes up the front end to use it. A broken
+// edit keeps the config as it was
+pub(crate) fn watch(notify: Notify) {
+    let Some(file) = file() else {
+        return;
+    };
+    get();
+    thread::spawn(move || {
+        let mut last = stamp(&file);
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let stamp = stamp(&file);
+            if stamp == last {
+                continue;
+            }
+            last = stamp;
+            match load() {
+                Ok(config) => *current().write().unwrap() = Arc::new(config),
+                Err(e) => log::error!("{e}"),
+            }
+            notify.notify();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn settings_left_out_keep_their_defaults() {
+        let config = Config::from_json(&json!({"font_size": 14, "page_size": 500})).unwrap();
+        assert_eq!((config.font_size, config.page_size), (14., 500));
+        assert_eq!(config.row_height, ROW_HEIGHT);
+        assert_eq!(config.theme, Theme::default());
+        assert_eq!(config.action("e", false), Some(Action::Edit));
+    }
+
+    #[test]
+    fn colors_go_on_top_of_the_theme() {
+        let config = Config::from_json(&json!({
+            "theme": "light",
+            "highlights": "tol",
+            "colors": {"background": "#102030", "edited": "#FFffFF"},
+        }))
+        .unwrap();
+        let light = Theme::named("light").unwrap();
+        assert_eq!(config.theme.background, [16, 32, 48]);
+        assert_eq!(config.theme.cell_text, light.cell_text);
+        assert_eq!(
+            config.theme.highlights.plot,
+            Theme::palette("tol").unwrap().plot
+        );
+        assert_eq!(config.theme.highlights.edited, [255, 255, 255]);
+    }
+
+    #[test]
+    fn bound_keys_replace_the_default_ones() {
+        let config = Config::from_json(&json!({
+            "keys": {"promote": ["P", "ctrl+p"], "hide": "p"},
+        }))
+        .unwrap();
+        assert_eq!(config.action("P", false), Some(Action::Promote));
+        assert_eq!(config.action("p", true), Some(Action::Promote));
+        // a bound key wins over the default that used it
+        assert_eq!(config.action("p", false), Some(Action::Hide));
+        assert_eq!(config.action("x", false), None);
+        assert_eq!(config.key(Action::Promote).as_deref(), Some("P"));
+        assert_eq!(config.key(Action::Hide).as_deref(), Some("p"));
+
+        let unbound = Config::from_json(&json!({"keys": {"pin": []}})).unwrap();
+        assert_eq!(unbound.key(Action::Pin), None);
+        assert_eq!(unbound.key(Action::Undo).as_deref(), Some("ctrl+z"));
+    }
+
+    #[test]
+    fn mistakes_are_told() {
+        let error = |value| Config::from_json(&value).unwrap_err();
+        assert_eq!(
+            error(json!({"column_width": 10})),
+            "column_width should be a number of at least 20"
+        );
+        assert_eq!(
+            error(json!({"font_size": "big"})),
+            "font_size should be a number of at least 1"
+        );
+        assert_eq!(
+            error(json!({"theme": "neon"})),
+            "there is no theme called neon"
+        );
+        assert_eq!(
+            error(json!({"colors": {"cell": "red"}})),
+            "colors.cell should look like \"#rrggbb\""
+        );
+        assert_eq!(
+            error(json!({"keys": {"fly": "f"}})),
+            "there is no action called fly"
+        );
+        assert_eq!(
+            error(json!({"keys": {"pin": "ctrl+ff"}})),
+            "keys.pin should be a key like \"e\" or \"ctrl+e\""
+        );
+    }
+
+    #[test]
+    fn colors_are_six_hex_digits() {
+        assert_eq!(parse_color("#00ff7F"), Some([0, 255, 127]));
+        assert_eq!(parse_color("00ff7f"), None);
+        assert_eq!(parse_color("#0f7"), None);
+        assert_eq!(parse_color("#00ff7g"), None);
+        assert_eq!(parse_color("#00ff7f0"), None);
+        assert_eq!(parse_color("#ééé"), None);
+    }
+}
@@ -0,0 +1,732 @@
+use std::{any::Any, error::Error, fmt, fs::File, io::Read, sync::Arc};
+
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{
+        array::{
+            Array, ArrayRef, BinaryBuilder, BooleanBuilder, Date32Builder, Float64Builder,
+            Int64Builder, StringArray, StringBuilder, TimestampMicrosecondBuilder,
+        },
+        datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
+        record_batch::{RecordBatch, RecordBatchOptions},
+    },
+    common::{exec_err, plan_err},
+    datasource::{TableProvider, TableType},
+    error::{DataFusionError, Result},
+    execution::{context::SessionState, TaskContext},
+    physical_expr::PhysicalSortExpr,
+    physical_plan::{
+        stream::RecordBatchReceiverStream, DisplayAs, DisplayFormatType, ExecutionPlan,
+        Partitioning, SendableRecordBatchStream,
+    },
+    prelude::*,
+};
+
+// rows per batch streamed from the database
+const BATCH_ROWS: usize = 8192;
+
+#[derive(Debug, Clone, Copy)]
+enum Engine {
+    Sqlite,
+    DuckDb,
+}
+
+// told apart by their header rather than the extension, `.db` is used for both. Only
+// regular files are looked at, reading a pipe would take its data away
+fn engine(path: &str) -> Option<Engine> {
+    let mut file = File::open(path).ok()?;
+    if !file.metadata().ok()?.is_file() {
+        return None;
+    }
+    let mut header = [0; 16];
+    file.read_exact(&mut header).ok()?;
+    if header.starts_with(b"SQLite format 3\0") {
+        Some(Engine::Sqlite)
+    } else if &header[8..12] == b"DUCK" {
+        Some(Engine::DuckDb)
+    } else {
+        None
+    }
+}
+
+// `shop.sqlite#orders` is one table of a database file, the file on its own its first table
+pub(crate) fn split(path: &str) -> Option<(&str, Option<&str>)> {
+    if engine(path).is_some() {
+        return Some((path, None));
+    }
+    let (file, table) = path.rsplit_once('#')?;
+    engine(file).map(|_| (file, Some(table)))
+}
+
+// every table of a database file as a path of its own, any other path as it is
+pub(crate) fn expand(path: &str) -> Vec<String> {
+    let Some((file, None)) = split(path) else {
+        return vec![path.to_string()];
+    };
+    match tables(file) {
+        Ok(tables) if !tables.is_empty() => tables.iter().map(|t| format!("{file}#{t}")).collect(),
+        _ => vec![path.to_string()],
+    }
+}
+
+fn tables(file: &str) -> Result<Vec<String>> {
+    let Some(engine) = engine(file) else {
+        return plan_err!("{file} is not a database");
+    };
+    let sql = match engine {
+        Engine::Sqlite => {
+            "SELECT name FROM sqlite_master \
+             WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY name"
+        }
+        Engine::DuckDb => {
+            "SELECT table_name FROM information_schema.tables \
+             WHERE table_schema = 'main' ORDER BY table_name"
+        }
+    };
+    Ok(text_query(engine, file, sql, 1)?.remove(0))
+}
+
+fn table_or_first(file: &str, table: Option<&str>) -> Result<String> {
+    match table {
+        Some(table) => Ok(table.to_string()),
+        None => match tables(file)?.into_iter().next() {
+            Some(table) => Ok(table),
+            None => plan_err!("{file} has no tables"),
+        },
+    }
+}
+
+// the columns of a table's primary key, none for a view or a table without one
+pub(crate) fn primary_key(path: &str) -> Result<Vec<String>> {
+    let Some((file, table)) = split(path) else {
+        return Ok(vec![]);
+    };
+    let engine = engine(file).unwrap();
+    let table = table_or_first(file, table)?.replace('\'', "''");
+    let sql = match engine {
+        Engine::Sqlite => {
+            format!("SELECT name FROM pragma_table_info('{table}') WHERE pk > 0 ORDER BY pk")
+        }
+        Engine::DuckDb => format!(
+            "SELECT unnest(constraint_column_names) FROM duckdb_constraints() \
+             WHERE schema_name = 'main' AND table_name = '{table}' \
+             AND constraint_type = 'PRIMARY KEY'"
+        ),
+    };
+    Ok(text_query(engine, file, &sql, 1)?.remove(0))
+}
+
+// sqlite's own number for a row of a table without a primary key, the rows are read in the
+// same order. Views and duckdb tables have none
+pub(crate) fn rowid(path: &str, row: usize) -> Result<Option<String>> {
+    let Some((file, table)) = split(path) else {
+        return Ok(None);
+    };
+    if !matches!(engine(file), Some(Engine::Sqlite)) {
+        return Ok(None);
+    }
+    let table = quote(&table_or_first(file, table)?);
+    let sql = format!("SELECT rowid FROM {table} LIMIT 1 OFFSET {row}");
+    match text_query(Engine::Sqlite, file, &sql, 1) {
+        Ok(mut rowids) => Ok(rowids.remove(0).pop()),
+        // a view has no rowid to select
+        Err(_) => Ok(None),
+    }
+}
+
+// names and declared types of a table's columns
+fn columns(engine: Engine, file: &str, table: &str) -> Result<Vec<(String, String)>> {
+    let table = table.replace('\'', "''");
+    let sql = match engine {
+        Engine::Sqlite => {
+            format!("SELECT name, type FROM pragma_table_info('{table}') ORDER BY cid")
+        }
+        Engine::DuckDb => format!(
+            "SELECT column_name, data_type FROM information_schema.columns \
+             WHERE table_schema = 'main' AND table_name = '{table}' ORDER BY ordinal_position"
+        ),
+    };
+    let mut result = text_query(engine, file, &sql, 2)?;
+    let types = result.pop().unwrap();
+    let names = result.pop().unwrap();
+    Ok(names.into_iter().zip(types).collect())
+}
+
+fn text_query(engine: Engine, file: &str, sql: &str, ncols: usize) -> Result<Vec<Vec<String>>> {
+    let mut builders: Vec<_> = (0..ncols).map(|_| Builder::new(&DataType::Utf8)).collect();
+    // every row goes into the same builders
+    query(engine, file, sql, &mut builders, &mut |_, _| Ok(true))?;
+    Ok(builders
+        .iter_mut()
+        .map(|builder| {
+            let array = builder.finish();
+            let strings = array.as_any().downcast_ref::<StringArray>().unwrap();
+            strings
+                .iter()
+                .map(|s| s.unwrap_or_default().to_string())
+                .collect()
+        })
+        .collect())
+}
+
+// sqlite's type affinity rules, so a column reads the way sqlite itself would store it
+fn sqlite_type(declared: &str) -> DataType {
+    let declared = declared.to_uppercase();
+    let has = |names: &[&str]| names.iter().any(|name| declared.contains(name));
+    if has(&["INT"]) {
+        DataType::Int64
+    } else if has(&["CHAR", "CLOB", "TEXT"]) || declared.is_empty() {
+        DataType::Utf8
+    } else if has(&["BLOB"]) {
+        DataType::Binary
+    } else if has(&["REAL", "FLOA", "DOUB"]) {
+        DataType::Float64
+    } else if has(&["BOOL"]) {
+        DataType::Boolean
+    } else if has(&["DATE", "TIME"]) {
+        // stored as text in whatever format the writer liked, cast from the profile panel
+        DataType::Utf8
+    } else {
+        DataType::Float64
+    }
+}
+
+fn duckdb_type(declared: &str) -> DataType {
+    match declared.to_uppercase().as_str() {
+        "TINYINT" | "SMALLINT" | "INTEGER" | "BIGINT" | "UTINYINT" | "USMALLINT" | "UINTEGER" => {
+            DataType::Int64
+        }
+        "UBIGINT" | "HUGEINT" | "FLOAT" | "DOUBLE" => DataType::Float64,
+        "BOOLEAN" => DataType::Boolean,
+        "DATE" => DataType::Date32,
+        "BLOB" => DataType::Binary,
+        t if t.starts_with("DECIMAL") => DataType::Float64,
+        t if t.starts_with("TIMESTAMP") => DataType::Timestamp(TimeUnit::Microsecond, None),
+        _ => DataType::Utf8,
+    }
+}
+
+pub(crate) fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+// duckdb hands back its own types, so the query already converts them to the column's
+fn select_expr(engine: Engine, name: &str, data_type: &DataType) -> String {
+    let column = quote(name);
+    match (engine, data_type) {
+        (Engine::Sqlite, _) => column,
+        (Engine::DuckDb, DataType::Int64) => format!("CAST({column} AS BIGINT)"),
+        (Engine::DuckDb, DataType::Float64) => format!("CAST({column} AS DOUBLE)"),
+        (Engine::DuckDb, DataType::Date32) => {
+            format!("CAST({column} - DATE '1970-01-01' AS BIGINT)")
+        }
+        (Engine::DuckDb, DataType::Timestamp(..)) => {
+            format!("epoch_us(CAST({column} AS TIMESTAMP))")
+        }
+        (Engine::DuckDb, DataType::Utf8) => format!("CAST({column} AS VARCHAR)"),
+        (Engine::DuckDb, _) => column,
+    }
+}
+
+pub(crate) fn register(
+    ctx: &SessionContext,
+    name: &str,
+    file: &str,
+    table: Option<&str>,
+) -> Result<()> {
+    let Some(engine) = engine(file) else {
+        return plan_err!("{file} is not a database");
+    };
+    let table = table_or_first(file, table)?;
+    let fields: Vec<_> = columns(engine, file, &table)?
+        .into_iter()
+        .map(|(name, declared)| {
+            let data_type = match engine {
+                Engine::Sqlite => sqlite_type(&declared),
+                Engine::DuckDb => duckdb_type(&declared),
+            };
+            Field::new(name, data_type, true)
+        })
+        .collect();
+    if fields.is_empty() {
+        return plan_err!("no table {table} in {file}");
+    }
+    let provider = DatabaseTable {
+        engine,
+        file: file.to_string(),
+        table,
+        schema: Arc::new(Schema::new(fields)),
+    };
+    ctx.deregister_table(name)?;
+    ctx.register_table(name, Arc::new(provider))?;
+    Ok(())
+}
+
+// a table read through the database's own engine, with the projection and limit in its query
+// and the skipped rows moved there by `PushDown`
+struct DatabaseTable {
+    engine: Engine,
+    file: String,
+    table: String,
+    schema: SchemaRef,
+}
+
+#[async_trait]
+impl TableProvider for DatabaseTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        _state: &SessionState,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let schema = match projection {
+            Some(projection) => Arc::new(self.schema.project(projection)?),
+            None => self.schema.clone(),
+        };
+        Ok(Arc::new(DatabaseExec {
+            engine: self.engine,
+            file: self.file.clone(),
+            table: self.table.clone(),
+            schema,
+            offset: 0,
+            limit,
+        }))
+    }
+}
+
+// one select on the database file, streamed a batch at a time
+#[derive(Debug, Clone)]
+pub(crate) struct DatabaseExec {
+    engine: Engine,
+    file: String,
+    table: String,
+    schema: SchemaRef,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl DatabaseExec {
+    fn sql(&self) -> String {
+        let columns: Vec<_> = self
+            .schema
+            .fields()
+            .iter()
+            .map(|f| select_expr(self.engine, f.name(), f.data_type()))
+            .collect();
+        // counting rows needs no columns, but a select does
+        let columns = if columns.is_empty() {
+            "1".to_string()
+        } else {
+            columns.join(", ")
+        };
+        let mut sql = format!("SELECT {columns} FROM {}", quote(&self.table));
+        match (self.limit, self.engine) {
+            (Some(limit), _) => sql.push_str(&format!(" LIMIT {limit}")),
+            // sqlite only takes an offset after a limit
+            (None, Engine::Sqlite) if self.offset > 0 => sql.push_str(" LIMIT -1"),
+            (None, _) => {}
+        }
+        if self.offset > 0 {
+            sql.push_str(&format!(" OFFSET {}", self.offset));
+        }
+        sql
+    }
+
+    // the same select, skipping rows in the database rather than reading them
+    pub(crate) fn skip(&self, skip: usize) -> Self {
+        Self {
+            offset: self.offset + skip,
+            limit: self.limit.map(|limit| limit.saturating_sub(skip)),
+            ..self.clone()
+        }
+    }
+}
+
+impl DisplayAs for DatabaseExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DatabaseExec: {}", self.sql())
+    }
+}
+
+impl ExecutionPlan for DatabaseExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(1)
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        _partition: usize,
+        _context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let (engine, file, sql) = (self.engine, self.file.clone(), self.sql());
+        log::debug!("{sql}");
+        let schema = self.schema.clone();
+        let mut stream = RecordBatchReceiverStream::builder(schema.clone(), 2);
+        let tx = stream.tx();
+        // the query stops once the stream is dropped, such as when a limit above it is met
+        stream.spawn_blocking(move || {
+            let mut builders: Vec<_> = schema
+                .fields()
+                .iter()
+                .map(|f| Builder::new(f.data_type()))
+                .collect();
+            query(
+                engine,
+                &file,
+                &sql,
+                &mut builders,
+                &mut |builders, nrows| {
+                    let batch = batch(&schema, builders, nrows)?;
+                    Ok(tx.blocking_send(Ok(batch)).is_ok())
+                },
+            )
+        });
+        Ok(stream.build())
+    }
+}
+
+fn batch(schema: &SchemaRef, builders: &mut [Builder], nrows: usize) -> Result<RecordBatch> {
+    let arrays = builders.iter_mut().map(Builder::finish).collect();
+    let options = RecordBatchOptions::new().with_row_count(Some(nrows));
+    Ok(RecordBatch::try_new_with_options(
+        schema.clone(),
+        arrays,
+        &options,
+    )?)
+}
+
+pub(crate) fn external(e: impl Error + Send + Sync + 'static) -> DataFusionError {
+    DataFusionError::External(Box::new(e))
+}
+
+// what a database hands back, once the query has cast anything else
+pub(crate) enum Value {
+    Null,
+    Int(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+    Bool(bool),
+}
+
+// sqlite doesn't hold a column to its declared type. Text that doesn't read as the column's
+// type and blobs become null, but a fraction in a column of integers is an error, cutting it
+// off would show a value that isn't in the database
+pub(crate) enum Builder {
+    Int(Int64Builder),
+    Float(Float64Builder),
+    Bool(BooleanBuilder),
+    Text(StringBuilder),
+    Blob(BinaryBuilder),
+    Date(Date32Builder),
+    Timestamp(TimestampMicrosecondBuilder),
+}
+
+impl Builder {
+    pub(crate) fn new(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Int64 => Self::Int(Int64Builder::new()),
+            DataType::Float64 => Self::Float(Float64Builder::new()),
+            DataType::Boolean => Self::Bool(BooleanBuilder::new()),
+            DataType::Binary => Self::Blob(BinaryBuilder::new()),
+            DataType::Date32 => Self::Date(Date32Builder::new()),
+            DataType::Timestamp(..) => Self::Timestamp(TimestampMicrosecondBuilder::new()),
+            _ => Self::Text(StringBuilder::new()),
+        }
+    }
+
+    pub(crate) fn append(&mut self, value: Value) -> Result<()> {
+        match (self, value) {
+            (Self::Int(b), Value::Int(v)) => b.append_value(v),
+            (Self::Int(b), Value::Real(v)) => match v.fract() == 0. && v.abs() < i64::MAX as f64 {
+                true => b.append_value(v as i64),
+                false => return exec_err!("{v} is in a column of integers"),
+            },
+            (Self::Int(b), Value::Bool(v)) => b.append_value(v as i64),
+            (Self::Int(b), Value::Text(v)) => b.append_option(v.trim().parse().ok()),
+            (Self::Int(b), _) => b.append_null(),
+            (Self::Float(b), Value::Real(v)) => b.append_value(v),
+            (Self::Float(b), Value::Int(v)) => b.append_value(v as f64),
+            (Self::Float(b), Value::Text(v)) => b.append_option(v.trim().parse().ok()),
+            (Self::Float(b), _) => b.append_null(),
+            (Self::Bool(b), Value::Bool(v)) => b.append_value(v),
+            (Self::Bool(b), Value::Int(v)) => b.append_value(v != 0),
+            (Self::Bool(b), Value::Text(v)) => b.append_option(v.trim().parse().ok()),
+            (Self::Bool(b), _) => b.append_null(),
+            (Self::Text(b), Value::Text(v)) => b.append_value(v),
+            (Self::Text(b), Value::Int(v)) => b.append_value(v.to_string()),
+            (Self::Text(b), Value::Real(v)) => b.append_value(v.to_string()),
+            (Self::Text(b), Value::Bool(v)) => b.append_value(v.to_string()),
+            (Self::Text(b), Value::Blob(v)) => b.append_value(String::from_utf8_lossy(&v)),
+            (Self::Text(b), Value::Null) => b.append_null(),
+            (Self::Blob(b), Value::Blob(v)) => b.append_value(v),
+            (Self::Blob(b), Value::Text(v)) => b.append_value(v),
+            (Self::Blob(b), _) => b.append_null(),
+            (Self::Date(b), Value::Int(v)) => b.append_option(i32::try_from(v).ok()),
+            (Self::Date(b), _) => b.append_null(),
+            (Self::Timestamp(b), Value::Int(v)) => b.append_value(v),
+            (Self::Timestamp(b), _) => b.append_null(),
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(&mut self) -> ArrayRef {
+        match self {
+            Self::Int(b) => Arc::new(b.finish()),
+            Self::Float(b) => Arc::new(b.finish()),
+            Self::Bool(b) => Arc::new(b.finish()),
+            Self::Text(b) => Arc::new(b.finish()),
+            Self::Blob(b) => Arc::new(b.finish()),
+            Self::Date(b) => Arc::new(b.finish()),
+            Self::Timestamp(b) => Arc::new(b.finish()),
+        }
+    }
+}
+
+// hands over the builders and the rows appended to them since the last batch, and says
+// whether to read on
+type Batch<'a> = dyn FnMut(&mut [Builder], usize) -> Result<bool> + 'a;
+
+// runs a read only query, appending the rows to one builder per column. Every `BATCH_ROWS`
+// rows and at the end they are handed to `batch`
+fn query(
+    engine: Engine,
+    file: &str,
+    sql: &str,
+    builders: &mut [Builder],
+    batch: &mut Batch,
+) -> Result<()> {
+    match engine {
+        Engine::Sqlite => sqlite_query(file, sql, builders, batch),
+        Engine::DuckDb => duckdb_query(file, sql, builders, batch),
+    }
+}
+
+fn sqlite_query(file: &str, sql: &str, builders: &mut [Builder], batch: &mut Batch) -> Result<()> {
+    use rusqlite::{types::ValueRef, Connection, OpenFlags};
+
+    let conn =
+        Connection::open_with_flags(file, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(external)?;
+    let mut statement = conn.prepare(sql).map_err(external)?;
+    let mut rows = statement.query([]).map_err(external)?;
+    let mut nrows = 0;
+    while let Some(row) = rows.next().map_err(external)? {
+        for (i, builder) in builders.iter_mut().enumerate() {
+            builder.append(match row.get_ref(i).map_err(external)? {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(v) => Value::Int(v),
+                ValueRef::Real(v) => Value::Real(v),
+                ValueRef::Text(v) => Value::Text(String::from_utf8_lossy(v).into_owned()),
+                ValueRef::Blob(v) => Value::Blob(v.to_vec()),
+            })?;
+        }
+        nrows += 1;
+        if nrows == BATCH_ROWS {
+            if !batch(builders, nrows)? {
+                return Ok(());
+            }
+            nrows = 0;
+        }
+    }
+    if nrows > 0 {
+        batch(builders, nrows)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "duckdb")]
+fn duckdb_query(file: &str, sql: &str, builders: &mut [Builder], batch: &mut Batch) -> Result<()> {
+    use duckdb::{types::Value as DuckValue, AccessMode, Config, Connection};
+
+    let config = Config::default()
+        .access_mode(AccessMode::ReadOnly)
+        .map_err(external)?;
+    let conn = Connection::open_with_flags(file, config).map_err(external)?;
+    let mut statement = conn.prepare(sql).map_err(external)?;
+    let mut rows = statement.query([]).map_err(external)?;
+    let mut nrows = 0;
+    while let Some(row) = rows.next().map_err(external)? {
+        for (i, builder) in builders.iter_mut().enumerate() {
+            builder.append(match row.get::<_, DuckValue>(i).map_err(external)? {
+                DuckValue::Boolean(v) => Value::Bool(v),
+                DuckValue::TinyInt(v) => Value::Int(v.into()),
+                DuckValue::SmallInt(v) => Value::Int(v.into()),
+                DuckValue::Int(v) => Value::Int(v.into()),
+                DuckValue::BigInt(v) => Value::Int(v),
+                DuckValue::UTinyInt(v) => Value::Int(v.into()),
+                DuckValue::USmallInt(v) => Value::Int(v.into()),
+                DuckValue::UInt(v) => Value::Int(v.into()),
+                DuckValue::Float(v) => Value::Real(v.into()),
+                DuckValue::Double(v) => Value::Real(v),
+                DuckValue::Text(v) => Value::Text(v),
+                DuckValue::Blob(v) => Value::Blob(v),
+                _ => Value::Null,
+            })?;
+        }
+        nrows += 1;
+        if nrows == BATCH_ROWS {
+            if !batch(builders, nrows)? {
+                return Ok(());
+            }
+            nrows = 0;
+        }
+    }
+    if nrows > 0 {
+        batch(builders, nrows)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "duckdb"))]
+fn duckdb_query(
+    file: &str,
+    _sql: &str,
+    _builders: &mut [Builder],
+    _batch: &mut Batch,
+) -> Result<()> {
+    plan_err!("can't read {file}, raam was built without the duckdb feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        arrow::{array::AsArray, datatypes::Int64Type},
+        physical_plan::{collect, displayable},
+    };
+
+    use super::*;
+    use crate::source;
+
+    // a sqlite file of `id` 0 to `nrows`, with a fraction in the integer column `n` at `bad`
+    fn sqlite(name: &str, nrows: i64, bad: i64) -> String {
+        let file = std::env::temp_dir().join(format!("raam-{name}-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&file);
+        let conn = rusqlite::Connection::open(&file).unwrap();
+        conn.execute_batch(&format!(
+            "CREATE TABLE t (id INTEGER, n INT); \
+             WITH RECURSIVE ids(id) AS (SELECT 0 UNION ALL SELECT id + 1 FROM ids WHERE id < {}) \
+             INSERT INTO t SELECT id, CASE WHEN id = {bad} THEN 2.5 ELSE id END FROM ids",
+            nrows - 1
+        ))
+        .unwrap();
+        file.to_string_lossy().to_string()
+    }
+
+    async fn run(ctx: &SessionContext, query: &str) -> Result<(String, Vec<i64>)> {
+        let plan = ctx.sql(query).await?.create_physical_plan().await?;
+        let shown = displayable(plan.as_ref()).indent(true).to_string();
+        let batches = collect(plan, ctx.task_ctx()).await?;
+        let values = batches
+            .iter()
+            .flat_map(|b| b.column(0).as_primitive::<Int64Type>().values().to_vec())
+            .collect();
+        Ok((shown, values))
+    }
+
+    #[tokio::test]
+    async fn skips_rows_in_the_database() {
+        let file = sqlite("offset", 20_000, -1);
+        let ctx = source::context();
+        register(&ctx, "t", &file, None).unwrap();
+        let (plan, ids) = run(&ctx, "SELECT id FROM t LIMIT 3 OFFSET 15000")
+            .await
+            .unwrap();
+        assert!(plan.contains("LIMIT 3 OFFSET 15000"), "{plan}");
+        assert_eq!(ids, [15000, 15001, 15002]);
+        // read in more than one batch
+        let (_, count) = run(&ctx, "SELECT count(*) FROM t").await.unwrap();
+        assert_eq!(count, [20_000]);
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_fraction_in_an_integer_column_is_an_error() {
+        let file = sqlite("fraction", 10, 4);
+        let ctx = source::context();
+        register(&ctx, "t", &file, None).unwrap();
+        let (_, whole) = run(&ctx, "SELECT n FROM t LIMIT 3").await.unwrap();
+        assert_eq!(whole, [0, 1, 2]);
+        let e = run(&ctx, "SELECT n FROM t").await.unwrap_err();
+        assert!(
+            e.to_string().contains("2.5 is in a column of integers"),
+            "{e}"
+        );
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn finds_a_row_by_its_key_or_rowid() {
+        let file = sqlite("key", 10, -1);
+        assert!(primary_key(&file).unwrap().is_empty());
+        assert_eq!(rowid(&file, 4).unwrap().as_deref(), Some("5"));
+        let conn = rusqlite::Connection::open(&file).unwrap();
+        conn.execute_batch("CREATE TABLE u (a TEXT, b INT, c INT, PRIMARY KEY (c, a))")
+            .unwrap();
+        assert_eq!(primary_key(&format!("{file}#u")).unwrap(), ["c", "a"]);
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn values_of_another_type_are_null() {
+        let file = sqlite("mixed", 1, -1);
+        let conn = rusqlite::Connection::open(&file).unwrap();
+        conn.execute_batch("INSERT INTO t VALUES (1, 'abc'), (2, X'00'), (3, ' 7 ')")
+            .unwrap();
+        let ctx = source::context();
+        register(&ctx, "t", &file, None).unwrap();
+        let batches = ctx
+            .sql("SELECT n FROM t")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let values: Vec<_> = batches
+            .iter()
+            .flat_map(|b| {
+                b.column(0)
+                    .as_primitive::<Int64Type>()
+                    .iter()
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(values, [Some(0), None, None, Some(7)]);
+        std::fs::remove_file(file).unwrap();
+    }
+}
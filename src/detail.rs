@@ -0,0 +1,64 @@
+use datafusion::arrow::{
+    array::{Array, AsArray},
+    datatypes::DataType,
+    util::display::{ArrayFormatter, FormatOptions},
+};
+
+const HEX_WIDTH: usize = 8;
+
+pub(crate) fn detail_text(array: &dyn Array, row: usize) -> String {
+    if array.is_null(row) {
+        return "null".to_string();
+    }
+    match array.data_type() {
+        DataType::Binary => hex_dump(array.as_binary::<i32>().value(row)),
+        DataType::LargeBinary => hex_dump(array.as_binary::<i64>().value(row)),
+        DataType::FixedSizeBinary(_) => hex_dump(array.as_fixed_size_binary().value(row)),
+        DataType::Utf8 => pretty_json(array.as_string::<i32>().value(row)),
+        DataType::LargeUtf8 => pretty_json(array.as_string::<i64>().value(row)),
+        _ => {
+            let format_options = FormatOptions::default().with_null("null");
+            match ArrayFormatter::try_new(array, &format_options) {
+                Ok(formatter) => formatter.value(row).to_string(),
+                Err(e) => format!("<{e}>"),
+            }
+        }
+    }
+}
+
+fn pretty_json(text: &str) -> String {
+    let trimmed = text.trim_start();
+    if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+        return text.to_string();
+    }
+    serde_json::from_str::<serde_json::Value>(text)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_else(|_| text.to_string())
+}
+
+fn hex_dump(bytes: &[u8]) -> String {
+    let lines: Vec<_> = bytes
+        .chunks(HEX_WIDTH)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<_> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|b| {
+                    if b.is_ascii_graphic() || *b == b' ' {
+                        *b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<width$}  {ascii}",
+                i * HEX_WIDTH,
+                hex.join(" "),
+                width = HEX_WIDTH * 3 - 1
+            )
+        })
+        .collect();
+    format!("{} bytes\n{}", bytes.len(), lines.join("\n"))
+}
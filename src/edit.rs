@@ -0,0 +1,518 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs::File,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use datafusion::{
+    arrow::{
+        array::Array,
+        csv,
+        datatypes::{DataType, Field},
+        json,
+        record_batch::RecordBatch,
+        util::display::{ArrayFormatter, FormatOptions},
+    },
+    common::{plan_err, ScalarValue},
+    error::{DataFusionError, Result},
+    parquet::arrow::ArrowWriter,
+    prelude::*,
+};
+use futures::StreamExt;
+
+use crate::{
+    database::{self, quote},
+    pipe, postgres,
+    schema::ColumnInfo,
+    source::{self, FileOptions},
+    spreadsheet,
+    worker::Request,
+};
+
+// how an exported update finds its row
+pub(crate) enum RowKey {
+    // the columns of the table's primary key
+    Columns(Vec<String>),
+    // sqlite's own row number, for the database table at the path
+    Rowid(String),
+    // the row's values, when the source has no key
+    Values,
+}
+
+impl RowKey {
+    pub(crate) async fn of(path: &str) -> Result<Self> {
+        let key = match postgres::is_url(path) {
+            true => postgres::primary_key(path).await?,
+            false => database::primary_key(path)?,
+        };
+        Ok(if !key.is_empty() {
+            Self::Columns(key)
+        } else if database::split(path).is_some() {
+            Self::Rowid(path.to_string())
+        } else {
+            Self::Values
+        })
+    }
+}
+
+// what an update of one row compares, the positions of the columns or the rowid
+enum Finder {
+    Columns(Vec<usize>),
+    Rowid(String),
+    // why the row can't be told apart from others
+    Lost(String),
+}
+
+impl Finder {
+    async fn new(
+        table_df: &DataFrame,
+        key: &RowKey,
+        row: usize,
+        names: &[String],
+        values: &[ScalarValue],
+    ) -> Result<Self> {
+        if let RowKey::Columns(key) = key {
+            let columns: Option<Vec<_>> = key
+                .iter()
+                .map(|k| names.iter().position(|n| n == k))
+                .collect();
+            if let Some(columns) = columns {
+                return Ok(match columns.iter().any(|&j| values[j].is_null()) {
+                    true => Self::Lost("its key is empty".to_string()),
+                    false => Self::Columns(columns),
+                });
+            }
+        }
+        if let RowKey::Rowid(path) = key {
+            if let Some(rowid) = database::rowid(path, row)? {
+                return Ok(Self::Rowid(rowid));
+            }
+        }
+        // floats rarely read back as the same literal, and nested values have none
+        let columns: Vec<_> = (0..names.len())
+            .filter(|&j| {
+                let data_type = values[j].data_type();
+                !data_type.is_nested() && !data_type.is_floating()
+            })
+            .collect();
+        let Some(matching) = columns
+            .iter()
+            .map(|&j| match values[j].is_null() {
+                true => ident(&names[j]).is_null(),
+                false => ident(&names[j]).eq(lit(values[j].clone())),
+            })
+            .reduce(Expr::and)
+        else {
+            return Ok(Self::Lost("no column can find it".to_string()));
+        };
+        let count = table_df.clone().filter(matching)?.count().await?;
+        Ok(match count {
+            1 => Self::Columns(columns),
+            _ => Self::Lost(format!("{count} rows have its values")),
+        })
+    }
+}
+
+// a value typed into a cell of the view, which keeps its row number
+#[derive(Debug, Clone)]
+struct Edit {
+    row: usize,
+    column: String,
+    value: ScalarValue,
+}
+
+// edits are laid over the rows read from the table, the table itself is never written to
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Edits {
+    log: Vec<Edit>,
+    undone: Vec<Edit>,
+}
+
+// what the typed text becomes, a cell's value, the expression of a new column or a grouping
+pub(crate) enum Target {
+    Cell { row: usize, col: usize },
+    Column,
+    Group,
+}
+
+// the text being typed, and why the last try was turned down
+pub(crate) struct Editor {
+    pub(crate) target: Target,
+    pub(crate) text: String,
+    pub(crate) error: Option<String>,
+}
+
+impl Editor {
+    // starts from the value as it would be typed, nothing for null
+    pub(crate) fn new(array: &dyn Array, index: usize, row: usize, col: usize) -> Self {
+        let text = match ArrayFormatter::try_new(array, &FormatOptions::default()) {
+            Ok(formatter) if !array.is_null(index) => formatter.value(index).to_string(),
+            _ => String::new(),
+        };
+        Self {
+            target: Target::Cell { row, col },
+            text,
+            error: None,
+        }
+    }
+
+    pub(crate) fn column() -> Self {
+        Self {
+            target: Target::Column,
+            text: String::new(),
+            error: None,
+        }
+    }
+
+    pub(crate) fn group() -> Self {
+        Self {
+            target: Target::Group,
+            text: String::new(),
+            error: None,
+        }
+    }
+
+    pub(crate) fn title(&self, columns: &[ColumnInfo]) -> String {
+        match self.target {
+            Target::Cell { row, col } => {
+                let name = columns.get(col).map_or("", |c| c.name.as_str());
+                format!("{name}, row {}", row + 1)
+            }
+            Target::Column => "new column".to_string(),
+            Target::Group => "group by".to_string(),
+        }
+    }
+
+    pub(crate) fn request(self) -> Request {
+        match self.target {
+            Target::Cell { row, col } => Request::Edit(row, col, self.text),
+            Target::Column => Request::AddColumn(self.text),
+            Target::Group => Request::Group(self.text),
+        }
+    }
+}
+
+// the text in a cell becomes a value of the column's type, or says why it can't
+pub(crate) fn parse(text: &str, field: &Field) -> Result<ScalarValue, String> {
+    if text.is_empty() {
+        if !field.is_nullable() {
+            return Err(format!("{} can't be empty", field.name()));
+        }
+        return ScalarValue::try_from(field.data_type()).map_err(|e| e.to_string());
+    }
+    if field.data_type().is_nested() {
+        return Err(format!("{} holds nested values", field.name()));
+    }
+    ScalarValue::try_from_string(text.to_string(), field.data_type())
+        .map_err(|_| format!("{text:?} is not a valid {}", field.data_type()))
+}
+
+impl Edits {
+    pub(crate) fn push(&mut self, row: usize, column: String, value: ScalarValue) {
+        self.log.push(Edit { row, column, value });
+        self.undone.clear();
+    }
+
+    pub(crate) fn undo(&mut self) -> bool {
+        let Some(edit) = self.log.pop() else {
+            return false;
+        };
+        self.undone.push(edit);
+        true
+    }
+
+    pub(crate) fn redo(&mut self) -> bool {
+        let Some(edit) = self.undone.pop() else {
+            return false;
+        };
+        self.log.push(edit);
+        true
+    }
+
+    // the edited cells, by row and column of the view
+    pub(crate) fn cells(&self, df: &DataFrame) -> Vec<(usize, usize)> {
+        let mut cells = vec![];
+        for edit in &self.log {
+            let Some(col) = column_index(df, &edit.column) else {
+                continue;
+            };
+            if !cells.contains(&(edit.row, col)) {
+                cells.push((edit.row, col));
+            }
+        }
+        cells
+    }
+
+    // the batch's values with the edits to its rows over them, the latest edit of a cell wins.
+    // A value that no longer fits its column after a cast keeps the one read
+    pub(crate) fn apply(&self, batch: RecordBatch, offset: usize) -> Result<RecordBatch> {
+        let rows = offset..offset + batch.num_rows();
+        let edits: Vec<_> = self.log.iter().filter(|e| rows.contains(&e.row)).collect();
+        if edits.is_empty() {
+            return Ok(batch);
+        }
+        let schema = batch.schema();
+        let mut columns = batch.columns().to_vec();
+        for (column, field) in columns.iter_mut().zip(schema.fields()) {
+            let edits: Vec<_> = edits.iter().filter(|e| e.column == *field.name()).collect();
+            if edits.is_empty() {
+                continue;
+            }
+            let mut values = (0..batch.num_rows())
+                .map(|i| ScalarValue::try_from_array(column, i))
+                .collect::<Result<Vec<_>>>()?;
+            for edit in edits {
+                if let Ok(value) = edit.value.cast_to(field.data_type()) {
+                    values[edit.row - offset] = value;
+                }
+            }
+            *column = ScalarValue::iter_to_array(values)?;
+        }
+        Ok(RecordBatch::try_new(schema, columns)?)
+    }
+
+    // every row of the table with the edits over it, in a new file next to the one opened. Only
+    // the table's own columns are written, not the ones the view computes or promotes
+    pub(crate) async fn save(
+        &self,
+        table_df: DataFrame,
+        path: &str,
+        options: &FileOptions,
+    ) -> Result<PathBuf> {
+        let format = source::extension(path);
+        if !is_plain_file(path)
+            || !matches!(
+                format.as_str(),
+                "csv" | "tsv" | "json" | "ndjson" | "jsonl" | "parquet"
+            )
+        {
+            return plan_err!("{path} can't be saved, export the edits as sql instead");
+        }
+        let stem = PathBuf::from(path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let output = PathBuf::from(path).with_file_name(format!("{stem}.edited.{format}"));
+        let file = File::create(&output)?;
+        let schema = Arc::new(table_df.schema().into());
+        let mut writer = match format.as_str() {
+            "csv" => Writer::Csv(Box::new(options.csv_writer(b',').build(file))),
+            "tsv" => Writer::Csv(Box::new(options.csv_writer(b'\t').build(file))),
+            "parquet" => Writer::Parquet(Box::new(ArrowWriter::try_new(file, schema, None)?)),
+            _ => Writer::Json(json::LineDelimitedWriter::new(file)),
+        };
+        let mut stream = table_df.execute_stream().await?;
+        let mut offset = 0;
+        while let Some(batch) = stream.next().await {
+            let batch = batch?;
+            let rows = batch.num_rows();
+            writer.write(&self.apply(batch, offset)?)?;
+            offset += rows;
+        }
+        writer.finish()?;
+        Ok(output)
+    }
+
+    // an update per edit, finding the row by its key, or by its values as they are at that
+    // point. Only the table's own columns are in it, not the ones the view computes or promotes.
+    // Edits to rows that can't be told apart are left out, and said why
+    pub(crate) async fn sql(
+        &self,
+        table_df: &DataFrame,
+        table: &str,
+        key: &RowKey,
+    ) -> Result<(String, Vec<String>)> {
+        let names: Vec<_> = table_df
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect();
+        let mut rows: HashMap<usize, (Vec<ScalarValue>, Finder)> = HashMap::new();
+        let mut statements = String::new();
+        let mut skipped = vec![];
+        for edit in &self.log {
+            let Some(col) = column_index(table_df, &edit.column) else {
+                continue;
+            };
+            if let Entry::Vacant(entry) = rows.entry(edit.row) {
+                let values = read_row(table_df, edit.row).await?;
+                let finder = Finder::new(table_df, key, edit.row, &names, &values).await?;
+                if let Finder::Lost(reason) = &finder {
+                    skipped.push(format!("row {}: {reason}", edit.row + 1));
+                }
+                entry.insert((values, finder));
+            }
+            let (row, finder) = rows.get_mut(&edit.row).unwrap();
+            let conditions: Vec<_> = match finder {
+                Finder::Rowid(rowid) => vec![format!("rowid = {rowid}")],
+                Finder::Columns(columns) => columns
+                    .iter()
+                    .map(|&j| match row[j].is_null() {
+                        true => format!("{} IS NULL", quote(&names[j])),
+                        false => format!("{} = {}", quote(&names[j]), literal(&row[j])),
+                    })
+                    .collect(),
+                Finder::Lost(_) => continue,
+            };
+            statements.push_str(&format!(
+                "UPDATE {} SET {} = {} WHERE {};\n",
+                quote(table),
+                quote(&edit.column),
+                literal(&edit.value),
+                conditions.join(" AND ")
+            ));
+            row[col] = edit.value.clone();
+        }
+        Ok((statements, skipped))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.log.is_empty()
+    }
+}
+
+// the update statements for a table go next to its file, or in the working directory
+pub(crate) fn sql_path(path: &str, table: &str) -> PathBuf {
+    let file = database::split(path)
+        .or_else(|| spreadsheet::split(path))
+        .map_or(path, |(file, _)| file);
+    let name = format!("{table}.edits.sql");
+    let root = PathBuf::from(source::root(file));
+    if postgres::is_url(path) || pipe::is_pipe(path) {
+        PathBuf::from(name)
+    } else if root.is_dir() {
+        root.join(name)
+    } else {
+        root.with_file_name(name)
+    }
+}
+
+fn is_plain_file(path: &str) -> bool {
+    !pipe::is_pipe(path) && PathBuf::from(path).is_file()
+}
+
+pub(crate) fn column_index(df: &DataFrame, name: &str) -> Option<usize> {
+    df.schema().fields().iter().position(|f| f.name() == name)
+}
+
+async fn read_row(df: &DataFrame, row: usize) -> Result<Vec<ScalarValue>> {
+    let batches = df.clone().limit(row, Some(1))?.collect().await?;
+    let Some(batch) = batches.iter().find(|b| b.num_rows() > 0) else {
+        return plan_err!("row {} is gone", row + 1);
+    };
+    batch
+        .columns()
+        .iter()
+        .map(|c| ScalarValue::try_from_array(c, 0))
+        .collect()
+}
+
+fn literal(value: &ScalarValue) -> String {
+    let data_type = value.data_type();
+    if value.is_null() {
+        "NULL".to_string()
+    } else if data_type.is_numeric() || data_type == DataType::Boolean {
+        value.to_string()
+    } else {
+        format!("'{}'", value.to_string().replace('\'', "''"))
+    }
+}
+
+enum Writer {
+    Csv(Box<csv::Writer<File>>),
+    Json(json::LineDelimitedWriter<File>),
+    Parquet(Box<ArrowWriter<File>>),
+}
+
+impl Writer {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        match self {
+            Self::Csv(writer) => writer.write(batch)?,
+            Self::Json(writer) => writer.write(batch)?,
+            Self::Parquet(writer) => writer.write(batch)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Self::Csv(_) => {}
+            Self::Json(mut writer) => writer.finish()?,
+            Self::Parquet(writer) => {
+                writer.close()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::array::{ArrayRef, Float64Array, Int64Array, StringArray};
+
+    use super::*;
+
+    fn table(columns: Vec<(&str, ArrayRef)>) -> DataFrame {
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        SessionContext::new().read_batch(batch).unwrap()
+    }
+
+    fn edited(row: usize, column: &str, value: ScalarValue) -> Edits {
+        let mut edits = Edits::default();
+        edits.push(row, column.to_string(), value);
+        edits
+    }
+
+    #[tokio::test]
+    async fn an_update_finds_a_null_and_leaves_floats_out() {
+        let df = table(vec![
+            ("name", Arc::new(StringArray::from(vec![Some("a"), None]))),
+            ("x", Arc::new(Float64Array::from(vec![0.1, f64::NAN]))),
+        ]);
+        let edits = edited(1, "name", ScalarValue::from("b"));
+        let (sql, skipped) = edits.sql(&df, "t", &RowKey::Values).await.unwrap();
+        assert_eq!(
+            sql,
+            "UPDATE \"t\" SET \"name\" = 'b' WHERE \"name\" IS NULL;\n"
+        );
+        assert!(skipped.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_row_of_floats_only_is_left_out() {
+        let df = table(vec![("x", Arc::new(Float64Array::from(vec![0.1, 0.2])))]);
+        let edits = edited(0, "x", ScalarValue::from(1.5));
+        let (sql, skipped) = edits.sql(&df, "t", &RowKey::Values).await.unwrap();
+        assert_eq!(sql, "");
+        assert_eq!(skipped, ["row 1: no column can find it"]);
+    }
+
+    #[tokio::test]
+    async fn rows_with_the_same_values_are_left_out() {
+        let df = table(vec![("name", Arc::new(StringArray::from(vec!["a", "a"])))]);
+        let edits = edited(1, "name", ScalarValue::from("b"));
+        let (sql, skipped) = edits.sql(&df, "t", &RowKey::Values).await.unwrap();
+        assert_eq!(sql, "");
+        assert_eq!(skipped, ["row 2: 2 rows have its values"]);
+    }
+
+    #[tokio::test]
+    async fn the_key_finds_the_row_as_it_was_edited() {
+        let df = table(vec![
+            ("id", Arc::new(Int64Array::from(vec![1, 2]))),
+            ("x", Arc::new(Float64Array::from(vec![0.1, 0.2]))),
+        ]);
+        let mut edits = edited(1, "id", ScalarValue::from(3i64));
+        edits.push(1, "x".to_string(), ScalarValue::from(1.5));
+        let key = RowKey::Columns(vec!["id".to_string()]);
+        let (sql, skipped) = edits.sql(&df, "t", &key).await.unwrap();
+        assert_eq!(
+            sql,
+            "UPDATE \"t\" SET \"id\" = 3 WHERE \"id\" = 2;\n\
+             UPDATE \"t\" SET \"x\" = 1.5 WHERE \"id\" = 3;\n"
+        );
+        assert!(skipped.is_empty());
+    }
+}
@@ -0,0 +1,277 @@
+use datafusion::{
+    common::ScalarValue,
+    error::Result,
+    logical_expr::{expr_fn, when},
+    prelude::*,
+};
+
+// distinct values of the pivot column spread across headers, the rest are left out
+const MAX_PIVOT_VALUES: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Count,
+    Sum,
+    Mean,
+    Min,
+    Max,
+    // an approximate percentile, from 0 to 100
+    Percentile(u8),
+}
+
+impl Function {
+    fn parse(name: &str) -> Option<Self> {
+        let function = match name.to_lowercase().as_str() {
+            "count" => Self::Count,
+            "sum" => Self::Sum,
+            "mean" | "avg" => Self::Mean,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "median" => Self::Percentile(50),
+            name => Self::Percentile(name.strip_prefix('p')?.parse().ok().filter(|p| *p <= 100)?),
+        };
+        Some(function)
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Self::Count => "count".to_string(),
+            Self::Sum => "sum".to_string(),
+            Self::Mean => "mean".to_string(),
+            Self::Min => "min".to_string(),
+            Self::Max => "max".to_string(),
+            Self::Percentile(p) => format!("p{p}"),
+        }
+    }
+
+    fn expr(&self, value: Expr) -> Expr {
+        match self {
+            Self::Count => expr_fn::count(value),
+            Self::Sum => expr_fn::sum(value),
+            Self::Mean => expr_fn::avg(value),
+            Self::Min => expr_fn::min(value),
+            Self::Max => expr_fn::max(value),
+            Self::Percentile(p) => expr_fn::approx_percentile_cont(value, lit(*p as f64 / 100.)),
+        }
+    }
+}
+
+// `sum(amount), p90(price) by region, product pivot month`. Without a column `count` counts
+// rows, and without any aggregates the rows of each group are counted
+#[derive(Debug, Clone)]
+pub(crate) struct Grouping {
+    aggregates: Vec<(Function, Option<String>)>,
+    keys: Vec<String>,
+    pivot: Option<String>,
+}
+
+pub(crate) fn parse(text: &str) -> Result<Grouping, String> {
+    let (text, pivot) = match split_word(text, "pivot") {
+        Some((text, pivot)) => (text, Some(pivot.trim().to_string())),
+        None => (text, None),
+    };
+    let (aggregates, keys) = split_word(text, "by").unwrap_or((text, ""));
+    let mut parsed = vec![];
+    for aggregate in list(aggregates) {
+        let (name, column) = match aggregate.split_once('(') {
+            Some((name, column)) => match column.strip_suffix(')') {
+                Some(column) => (name.trim(), Some(column.trim())),
+                None => return Err(format!("{aggregate} is missing a closing parenthesis")),
+            },
+            None => (aggregate, None),
+        };
+        let Some(function) = Function::parse(name) else {
+            return Err(format!(
+                "{name} is not one of count, sum, mean, min, max, median or p0 to p100"
+            ));
+        };
+        let column = column.filter(|c| !c.is_empty() && *c != "*");
+        if column.is_none() && function != Function::Count {
+            return Err(format!("{name} needs a column, as in {name}(price)"));
+        }
+        parsed.push((function, column.map(str::to_string)));
+    }
+    if parsed.is_empty() {
+        parsed.push((Function::Count, None));
+    }
+    let keys: Vec<_> = list(keys).map(str::to_string).collect();
+    if keys.is_empty() && pivot.is_none() {
+        return Err("group by at least one column, as in sum(amount) by region".to_string());
+    }
+    Ok(Grouping {
+        aggregates: parsed,
+        keys,
+        pivot: pivot.filter(|p| !p.is_empty()),
+    })
+}
+
+// splits at the last stand-alone word, in any case
+fn split_word<'a>(text: &'a str, word: &str) -> Option<(&'a str, &'a str)> {
+    // ascii only, so the byte offsets match the text's
+    let lower = text.to_ascii_lowercase();
+    let i = lower.rmatch_indices(word).map(|(i, _)| i).find(|&i| {
+        let before = lower[..i].chars().next_back();
+        let after = lower[i + word.len()..].chars().next();
+        before.is_none_or(char::is_whitespace) && after.is_none_or(char::is_whitespace)
+    })?;
+    Some((&text[..i], &text[i + word.len()..]))
+}
+
+fn list(text: &str) -> impl Iterator<Item = &str> {
+    text.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+impl Grouping {
+    // a short description of the grouping, for the tab and status line
+    pub(crate) fn describe(&self) -> String {
+        let aggregates: Vec<_> = self.aggregates.iter().map(aggregate_name).collect();
+        let mut text = aggregates.join(", ");
+        if !self.keys.is_empty() {
+            text.push_str(&format!(" by {}", self.keys.join(", ")));
+        }
+        if let Some(pivot) = &self.pivot {
+            text.push_str(&format!(" pivot {pivot}"));
+        }
+        text
+    }
+
+    // a row per group, ordered by the grouping columns
+    pub(crate) async fn apply(&self, df: &DataFrame) -> Result<DataFrame> {
+        let keys: Vec<_> = self.keys.iter().map(ident).collect();
+        let aggregates = match &self.pivot {
+            Some(pivot) => self.pivot_aggregates(df, pivot).await?,
+            None => self
+                .aggregates
+                .iter()
+                .map(|aggregate| {
+                    let (function, column) = aggregate;
+                    function
+                        .expr(value(column))
+                        .alias(aggregate_name(aggregate))
+                })
+                .collect(),
+        };
+        let sort = keys.iter().map(|k| k.clone().sort(true, false)).collect();
+        df.clone().aggregate(keys, aggregates)?.sort(sort)
+    }
+
+    // each aggregate once per value of the pivot column, over the rows holding that value
+    async fn pivot_aggregates(&self, df: &DataFrame, pivot: &str) -> Result<Vec<Expr>> {
+        let values = df
+            .clone()
+            .select(vec![ident(pivot)])?
+            .distinct()?
+            .sort(vec![ident(pivot).sort(true, false)])?
+            .limit(0, Some(MAX_PIVOT_VALUES))?
+            .collect()
+            .await?;
+        let mut exprs = vec![];
+        for batch in values {
+            for i in 0..batch.num_rows() {
+                let spread = ScalarValue::try_from_array(batch.column(0), i)?;
+                let matches = match spread.is_null() {
+                    true => ident(pivot).is_null(),
+                    false => ident(pivot).eq(lit(spread.clone())),
+                };
+                for aggregate in &self.aggregates {
+                    let (function, column) = aggregate;
+                    let picked = when(matches.clone(), value(column)).end()?;
+                    let name = format!("{spread} {}", aggregate_name(aggregate));
+                    exprs.push(function.expr(picked).alias(name));
+                }
+            }
+        }
+        Ok(exprs)
+    }
+
+    // the rows of the group in a row of the grouped table, and the group's values
+    pub(crate) async fn filter(&self, grouped: &DataFrame, row: usize) -> Result<(Expr, String)> {
+        let batches = grouped.clone().limit(row, Some(1))?.collect().await?;
+        let mut filter = lit(true);
+        let mut values = vec![];
+        for batch in batches.iter().filter(|b| b.num_rows() > 0) {
+            for key in &self.keys {
+                let Some(column) = batch.column_by_name(key) else {
+                    continue;
+                };
+                let value = ScalarValue::try_from_array(column, 0)?;
+                values.push(format!("{key} = {value}"));
+                filter = filter.and(match value.is_null() {
+                    true => ident(key).is_null(),
+                    false => ident(key).eq(lit(value)),
+                });
+            }
+        }
+        Ok((filter, values.join(", ")))
+    }
+}
+
+fn aggregate_name((function, column): &(Function, Option<String>)) -> String {
+    match column {
+        Some(column) => format!("{}({column})", function.name()),
+        None => function.name(),
+    }
+}
+
+// count without a column counts rows
+fn value(column: &Option<String>) -> Expr {
+    column.as_ref().map_or(lit(1), ident)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groupings_are_read_from_text() {
+        let grouping = parse("sum(amount), P90( price ) BY region, product pivot month").unwrap();
+        assert_eq!(
+            grouping.aggregates,
+            [
+                (Function::Sum, Some("amount".to_string())),
+                (Function::Percentile(90), Some("price".to_string())),
+            ]
+        );
+        assert_eq!(grouping.keys, ["region", "product"]);
+        assert_eq!(grouping.pivot.as_deref(), Some("month"));
+
+        // rows are counted without aggregates, and a word inside a name doesn't split it
+        let grouping = parse("by byline").unwrap();
+        assert_eq!(grouping.aggregates, [(Function::Count, None)]);
+        assert_eq!(grouping.keys, ["byline"]);
+        assert!(parse("avg(x) pivot m").unwrap().keys.is_empty());
+    }
+
+    #[test]
+    fn mistakes_are_told() {
+        let error = |text| parse(text).unwrap_err();
+        assert_eq!(error("sum by a"), "sum needs a column, as in sum(price)");
+        assert_eq!(
+            error("sum(x by a"),
+            "sum(x is missing a closing parenthesis"
+        );
+        assert!(error("total(x) by a").starts_with("total is not one of"));
+        assert!(error("p101(x) by a").starts_with("p101 is not one of"));
+        assert_eq!(
+            error("sum(x)"),
+            "group by at least one column, as in sum(amount) by region"
+        );
+    }
+
+    #[test]
+    fn a_description_parses_back_to_the_same_grouping() {
+        for (text, described) in [
+            ("count(*) by a", "count by a"),
+            ("MEDIAN(x), avg(y) by a, b", "p50(x), mean(y) by a, b"),
+            ("max(x) by a pivot m", "max(x) by a pivot m"),
+            ("min(x) pivot m", "min(x) pivot m"),
+        ] {
+            let grouping = parse(text).unwrap();
+            assert_eq!(grouping.describe(), described);
+            let again = parse(&grouping.describe()).unwrap();
+            assert_eq!(again.aggregates, grouping.aggregates);
+            assert_eq!(again.keys, grouping.keys);
+            assert_eq!(again.pivot, grouping.pivot);
+        }
+    }
+}
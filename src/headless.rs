@@ -0,0 +1,140 @@
+use std::{error::Error, fs::File, io::BufWriter, ops::Range, path::Path};
+
+use crate::{source::FileOptions, state::State};
+
+fn instance() -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    })
+}
+
+// the rows drawn offscreen, as width, height and rgba pixels
+fn image(
+    path: String,
+    options: FileOptions,
+    rows: Range<usize>,
+    width: Option<u32>,
+) -> Result<(u32, u32, Vec<u8>), Box<dyn Error>> {
+    let mut state = pollster::block_on(State::headless(
+        instance(),
+        path,
+        options,
+        rows.len(),
+        width,
+    ))?;
+    state.scroll_to_row(rows.start);
+    state.wait_for_data()?;
+    state.prepare();
+    state.render()?;
+    let rgba = state
+        .read_rgba()
+        .ok_or("could not read back the rendered image")?;
+    Ok((state.size.width, state.size.height, rgba))
+}
+
+fn write_png(output: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(output)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(rgba)?;
+    Ok(())
+}
+
+pub(crate) fn render(
+    path: String,
+    options: FileOptions,
+    rows: Range<usize>,
+    output: &Path,
+    width: Option<u32>,
+) -> Result<(), Box<dyn Error>> {
+    let (width, height, rgba) = image(path, options, rows, width)?;
+    write_png(output, width, height, &rgba)?;
+    log::info!("wrote {}", output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use serde_json::Value;
+
+    use super::*;
+
+    const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/grid.png");
+    // rasterizers blend the edges of glyphs a little differently, so a few pixels may be
+    // off. Other rows' text already differs in about 2% of them
+    const TOLERANCE: u8 = 48;
+    const MAX_DIFFERENT: f64 = 0.005;
+
+    fn has_adapter() -> bool {
+        let instance = instance();
+        pollster::block_on(async {
+            for force_fallback_adapter in [false, true] {
+                let options = wgpu::RequestAdapterOptions {
+                    force_fallback_adapter,
+                    ..Default::default()
+                };
+                if instance.request_adapter(&options).await.is_some() {
+                    return true;
+                }
+            }
+            false
+        })
+    }
+
+    fn read_png(path: &Path) -> Result<(u32, u32, Vec<u8>), Box<dyn Error>> {
+        let decoder = png::Decoder::new(File::open(path)?);
+        let mut reader = decoder.read_info()?;
+        let mut rgba = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut rgba)?;
+        rgba.truncate(info.buffer_size());
+        Ok((info.width, info.height, rgba))
+    }
+
+    // set `UPDATE_GOLDEN=1` to write the image again after changing how the grid looks, or
+    // on a machine whose fonts draw the text differently
+    #[test]
+    fn renders_like_the_golden_image() {
+        if !has_adapter() {
+            eprintln!("no graphics adapter, skipping the golden image test");
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("raam-golden-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("grid.csv");
+        let mut text = String::from("id,name,amount\n");
+        for i in 0..20 {
+            text.push_str(&format!("{i},item {},{}\n", i % 3, i * i % 17));
+        }
+        fs::write(&csv, text).unwrap();
+
+        // the default config, not the one of whoever runs the test
+        std::env::set_var("XDG_CONFIG_HOME", &dir);
+        let path = csv.to_string_lossy().to_string();
+        let (width, height, rgba) =
+            image(path, FileOptions::from_json(&Value::Null), 2..7, None).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let golden = PathBuf::from(GOLDEN);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() || !golden.exists() {
+            fs::create_dir_all(golden.parent().unwrap()).unwrap();
+            write_png(&golden, width, height, &rgba).unwrap();
+            return;
+        }
+        let (golden_width, golden_height, golden_rgba) = read_png(&golden).unwrap();
+        assert_eq!((width, height), (golden_width, golden_height));
+        let different = rgba
+            .chunks(4)
+            .zip(golden_rgba.chunks(4))
+            .filter(|(a, b)| a.iter().zip(*b).any(|(a, b)| a.abs_diff(*b) > TOLERANCE))
+            .count();
+        let share = different as f64 / (width * height) as f64;
+        assert!(
+            share <= MAX_DIFFERENT,
+            "{:.1}% of the pixels differ from {GOLDEN}",
+            share * 100.
+        );
+    }
+}
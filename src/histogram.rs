@@ -0,0 +1,152 @@
+use datafusion::{
+    arrow::{
+        array::{Array, AsArray},
+        datatypes::{DataType, Float64Type, Int64Type},
+    },
+    common::plan_err,
+    error::{DataFusionError, Result},
+    prelude::*,
+};
+
+use crate::line;
+
+pub(crate) const HIST_HEIGHT: f32 = 20.;
+const BINS: usize = 20;
+const TOP_K: usize = 5;
+
+#[derive(Debug, Clone)]
+pub(crate) enum Distribution {
+    Histogram(Vec<u64>),
+    TopValues { counts: Vec<u64>, total: u64 },
+}
+
+impl Distribution {
+    pub(crate) async fn compute(df: DataFrame, column: usize) -> Result<Option<Self>> {
+        // the columns may have changed since the plot was asked for
+        let Some(field) = df.schema().fields().get(column).cloned() else {
+            return plan_err!("there is no column {}", column + 1);
+        };
+        let c = ident(field.name());
+        let data_type = field.data_type();
+        if data_type.is_temporal() {
+            let x = cast(cast(c, DataType::Int64), DataType::Float64);
+            Self::histogram(df, x).await.map(Some)
+        } else if data_type.is_numeric() {
+            Self::histogram(df, cast(c, DataType::Float64))
+                .await
+                .map(Some)
+        } else if data_type.is_nested() {
+            Ok(None)
+        } else {
+            Self::top_values(df, c).await.map(Some)
+        }
+    }
+
+    async fn histogram(df: DataFrame, x: Expr) -> Result<Self> {
+        let mut counts = vec![0; BINS];
+        let batches = df
+            .clone()
+            .aggregate(vec![], vec![min(x.clone()), max(x.clone())])?
+            .collect()
+            .await?;
+        let Some(batch) = batches.first().filter(|b| b.num_rows() > 0) else {
+            return Ok(Self::Histogram(counts));
+        };
+        let (lo, hi) = (
+            batch.column(0).as_primitive::<Float64Type>(),
+            batch.column(1).as_primitive::<Float64Type>(),
+        );
+        if lo.is_null(0) {
+            return Ok(Self::Histogram(counts));
+        }
+        let (lo, hi) = (lo.value(0), hi.value(0));
+        let width = if hi > lo { hi - lo } else { 1. };
+
+        let bin = cast(
+            (x.clone() - lit(lo)) / lit(width) * lit(BINS as f64),
+            DataType::Int64,
+        );
+        let batches = df
+            .filter(x.is_not_null())?
+            .aggregate(vec![bin.alias("bin")], vec![count(lit(1))])?
+            .collect()
+            .await?;
+        for batch in batches.iter() {
+            let bins = batch.column(0).as_primitive::<Int64Type>();
+            let n = batch.column(1).as_primitive::<Int64Type>();
+            for i in 0..batch.num_rows() {
+                // the max value lands on the upper edge of the last bin
+                counts[(bins.value(i).max(0) as usize).min(BINS - 1)] += n.value(i) as u64;
+            }
+        }
+        Ok(Self::Histogram(counts))
+    }
+
+    async fn top_values(df: DataFrame, c: Expr) -> Result<Self> {
+        let batches = df
+            .clone()
+            .aggregate(vec![], vec![count(lit(1))])?
+            .collect()
+            .await?;
+        let total = batches.first().filter(|b| b.num_rows() > 0).map_or(0, |b| {
+            b.column(0).as_primitive::<Int64Type>().value(0) as u64
+        });
+        let batches = df
+            .aggregate(vec![c], vec![count(lit(1)).alias("n")])?
+            .sort(vec![col("n").sort(false, false)])?
+            .limit(0, Some(TOP_K))?
+            .collect()
+            .await?;
+        let counts = batches
+            .iter()
+            .flat_map(|b| {
+                b.column(1)
+                    .as_primitive::<Int64Type>()
+                    .values()
+                    .iter()
+                    .map(|n| *n as u64)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        Ok(Self::TopValues { counts, total })
+    }
+
+    pub(crate) fn instances(&self, left: f32, top: f32, width: f32) -> Vec<line::Instance> {
+        let height = HIST_HEIGHT - 4.;
+        let top = top + 2.;
+        match self {
+            Self::Histogram(counts) => {
+                let max = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+                let bar_width = width / counts.len() as f32;
+                counts
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, n)| **n > 0)
+                    .map(|(i, n)| {
+                        let bar_height = (*n as f32 / max * height).max(1.);
+                        line::Instance::new(
+                            (left + i as f32 * bar_width, top + height - bar_height),
+                            (bar_width - 1., bar_height),
+                            255.,
+                        )
+                    })
+                    .collect()
+            }
+            Self::TopValues { counts, total } => {
+                let total = (*total).max(1) as f32;
+                let mut x = left;
+                counts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, n)| {
+                        let segment = *n as f32 / total * width;
+                        let alpha = if i % 2 == 0 { 255. } else { 150. };
+                        let instance = line::Instance::new((x, top), (segment, height), alpha);
+                        x += segment;
+                        instance
+                    })
+                    .collect()
+            }
+        }
+    }
+}
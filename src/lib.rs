@@ -1,16 +1,83 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use cli::{Cli, Command};
 use state::State;
 use winit::{
     event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    event_loop::{ControlFlow, EventLoopBuilder},
     window::WindowBuilder,
 };
-// mod camera;
+mod camera;
+mod cast;
+mod cli;
+mod computed;
+mod config;
+mod database;
+mod detail;
+mod edit;
+mod group;
 // mod grid;
-// mod line;
+mod headless;
+mod histogram;
+mod line;
+mod nested;
+mod panel;
+mod pipe;
+mod postgres;
+mod profile;
+mod reload;
+mod schema;
+mod session;
+mod settings;
+mod source;
+mod spreadsheet;
 mod state;
+mod tab;
+mod target;
+mod theme;
+mod tui;
+mod viewport;
+mod worker;
 
 pub fn run() {
     env_logger::init();
+    let cli = Cli::parse();
+    if let Some(Command::Render {
+        path,
+        rows,
+        output,
+        width,
+        options,
+    }) = cli.command
+    {
+        if let Err(e) = headless::render(path, options, rows, &output, width) {
+            eprintln!("render failed: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let mut options = cli.options;
+    options.follow = cli.follow;
+    // a view file says what to open, and how
+    let session = match session::is_session(&cli.path) {
+        true => match session::load(&cli.path) {
+            Ok(session) => Some((PathBuf::from(&cli.path), session)),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        },
+        false => None,
+    };
+    if cli.tui {
+        if let Err(e) = tui::run(cli.path, options, session) {
+            eprintln!("tui failed: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let path = cli.path;
 
     // hack to not let window hang for a long time at startup
     let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
@@ -18,7 +85,9 @@ pub fn run() {
         ..Default::default()
     });
 
-    let event_loop = EventLoop::new().expect("EventLoop failed");
+    let event_loop = EventLoopBuilder::with_user_event()
+        .build()
+        .expect("EventLoop failed");
     let window = WindowBuilder::new()
         .with_title("raam")
         .with_inner_size(winit::dpi::PhysicalSize::new(512, 512))
@@ -30,7 +99,15 @@ pub fn run() {
         .enable_all()
         .build()
         .unwrap();
-    let mut state = rt.block_on(State::new(&window, instance));
+    let proxy = event_loop.create_proxy();
+    let opened = rt.block_on(State::new(&window, instance, path, options, session, proxy));
+    let mut state = match opened {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
 
     event_loop
         .run(|event, elwt| match event {
@@ -58,6 +135,8 @@ pub fn run() {
                     }
                 }
             }
+            // the data worker finished something
+            Event::UserEvent(()) => window.request_redraw(),
             _ => {}
         })
         .expect("event loop failed");
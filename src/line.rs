@@ -1,4 +1,10 @@
 use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::{Camera, CameraUniform},
+    theme::{self, Highlights},
+};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -29,12 +35,21 @@ pub(crate) const VERTICES: &[Vertex] = &[
 
 pub(crate) const INDICES: &[u16] = &[0, 1, 2, 1, 2, 3];
 
+// which of the theme's highlight colors an instance is drawn in
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Highlight {
+    Plot,
+    Selection,
+    Edited,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable, Debug)]
 pub(crate) struct Instance {
     offset: [f32; 2],
     scale: [f32; 2],
     alpha: f32,
+    highlight: u32,
 }
 
 impl Instance {
@@ -43,6 +58,14 @@ impl Instance {
             offset: [offset.0, offset.1],
             scale: [scale.0, scale.1],
             alpha,
+            highlight: Highlight::Plot as u32,
+        }
+    }
+
+    pub(crate) fn highlight(self, highlight: Highlight) -> Self {
+        Self {
+            highlight: highlight as u32,
+            ..self
         }
     }
 
@@ -67,7 +90,200 @@ impl Instance {
                     shader_location: 3,
                     format: wgpu::VertexFormat::Float32,
                 },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+            ],
+        }
+    }
+}
+
+// the highlight colors in linear space, in the order of `Highlight`
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable, Debug)]
+struct PaletteUniform {
+    colors: [[f32; 4]; 3],
+}
+
+impl From<&Highlights> for PaletteUniform {
+    fn from(value: &Highlights) -> Self {
+        let color = |rgb: [u8; 3]| {
+            let [r, g, b] = rgb.map(|c| theme::linear(c) as f32);
+            [r, g, b, 1.]
+        };
+        Self {
+            colors: [
+                color(value.plot),
+                color(value.selection),
+                color(value.edited),
             ],
         }
     }
 }
+
+pub(crate) struct LinePipeline {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    palette_buffer: wgpu::Buffer,
+    palette_bind_group: wgpu::BindGroup,
+    instance_buffer: wgpu::Buffer,
+    num_instances: u32,
+}
+
+impl LinePipeline {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        highlights: &Highlights,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
+
+        let camera_buffer = CameraUniform::from(&Camera::default()).to_buffer(device);
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&CameraUniform::bind_group_layout_desc());
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+            label: Some("camera_bind_group"),
+        });
+
+        let palette_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Palette buffer"),
+            contents: bytemuck::cast_slice(&[PaletteUniform::from(highlights)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let palette_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("palette_bind_group_layout"),
+            });
+        let palette_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &palette_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: palette_buffer.as_entire_binding(),
+            }],
+            label: Some("palette_bind_group"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Line Pipeline Layout"),
+            bind_group_layouts: &[&camera_bind_group_layout, &palette_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Line Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc(), Instance::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let instance_buffer = Self::create_instance_buffer(device, 1);
+
+        Self {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            camera_buffer,
+            camera_bind_group,
+            palette_buffer,
+            palette_bind_group,
+            instance_buffer,
+            num_instances: 0,
+        }
+    }
+
+    fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (capacity * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // a theme chosen while the window is open
+    pub(crate) fn set_highlights(&self, queue: &wgpu::Queue, highlights: &Highlights) {
+        queue.write_buffer(
+            &self.palette_buffer,
+            0,
+            bytemuck::cast_slice(&[PaletteUniform::from(highlights)]),
+        );
+    }
+
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        instances: &[Instance],
+    ) {
+        queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[CameraUniform::from(camera)]),
+        );
+        let size = std::mem::size_of_val(instances) as wgpu::BufferAddress;
+        if size > self.instance_buffer.size() {
+            self.instance_buffer = Self::create_instance_buffer(device, instances.len());
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(instances));
+        self.num_instances = instances.len() as u32;
+    }
+
+    pub(crate) fn render<'pass>(&'pass self, pass: &mut wgpu::RenderPass<'pass>) {
+        if self.num_instances == 0 {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        pass.set_bind_group(1, &self.palette_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        pass.draw_indexed(0..INDICES.len() as u32, 0, 0..self.num_instances);
+    }
+}
@@ -0,0 +1,107 @@
+use datafusion::{
+    arrow::{
+        array::{Array, AsArray},
+        datatypes::DataType,
+        util::display::{ArrayFormatter, FormatOptions},
+    },
+    common::plan_err,
+    error::{DataFusionError, Result},
+    prelude::*,
+};
+
+pub(crate) fn summary(array: &dyn Array, row: usize) -> Option<String> {
+    if !array.data_type().is_nested() {
+        return None;
+    }
+    if array.is_null(row) {
+        return Some("null".to_string());
+    }
+    let text = match array.data_type() {
+        DataType::List(_) => format!("[{} items]", array.as_list::<i32>().value(row).len()),
+        DataType::LargeList(_) => format!("[{} items]", array.as_list::<i64>().value(row).len()),
+        DataType::FixedSizeList(_, n) => format!("[{n} items]"),
+        DataType::Struct(fields) => {
+            let names: Vec<_> = fields.iter().map(|f| f.name().as_str()).collect();
+            format!("{{{}}}", names.join(", "))
+        }
+        DataType::Map(_, _) => format!("{{{} entries}}", array.as_map().value(row).len()),
+        _ => return None,
+    };
+    Some(text)
+}
+
+pub(crate) fn tree(array: &dyn Array, row: usize) -> String {
+    let mut out = String::new();
+    write_tree(array, row, 1, &mut out);
+    out
+}
+
+fn write_tree(array: &dyn Array, row: usize, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    if array.is_null(row) {
+        out.push_str(" null");
+        return;
+    }
+    let mut write_child = |label: String, child: &dyn Array, index: usize| {
+        out.push_str(&format!("\n{indent}{label}:"));
+        write_tree(child, index, depth + 1, out);
+    };
+    match array.data_type() {
+        DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(_, _) => {
+            let values = match array.data_type() {
+                DataType::List(_) => array.as_list::<i32>().value(row),
+                DataType::LargeList(_) => array.as_list::<i64>().value(row),
+                _ => array.as_fixed_size_list().value(row),
+            };
+            for i in 0..values.len() {
+                write_child(format!("[{i}]"), values.as_ref(), i);
+            }
+        }
+        DataType::Struct(fields) => {
+            let array = array.as_struct();
+            for (field, column) in fields.iter().zip(array.columns()) {
+                write_child(field.name().clone(), column.as_ref(), row);
+            }
+        }
+        DataType::Map(_, _) => {
+            let entries = array.as_map().value(row);
+            let keys =
+                ArrayFormatter::try_new(entries.column(0).as_ref(), &FormatOptions::default());
+            for i in 0..entries.len() {
+                let key = keys
+                    .as_ref()
+                    .map_or_else(|_| format!("[{i}]"), |k| k.value(i).to_string());
+                write_child(key, entries.column(1).as_ref(), i);
+            }
+        }
+        _ => {
+            let format_options = FormatOptions::default().with_null("null");
+            match ArrayFormatter::try_new(array, &format_options) {
+                Ok(formatter) => out.push_str(&format!(" {}", formatter.value(row))),
+                Err(e) => out.push_str(&format!(" <{e}>")),
+            }
+        }
+    }
+}
+
+pub(crate) fn promote_fields(df: DataFrame, column: usize) -> Result<DataFrame> {
+    let schema = df.schema().clone();
+    let DataType::Struct(fields) = schema.field(column).data_type() else {
+        return plan_err!("{} is not a struct column", schema.field(column).name());
+    };
+    let mut exprs = vec![];
+    for (j, field) in schema.fields().iter().enumerate() {
+        if j != column {
+            exprs.push(ident(field.name()));
+            continue;
+        }
+        for sub_field in fields.iter() {
+            exprs.push(ident(field.name()).field(sub_field.name()).alias(format!(
+                "{}.{}",
+                field.name(),
+                sub_field.name()
+            )));
+        }
+    }
+    df.select(exprs)
+}
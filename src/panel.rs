@@ -0,0 +1,118 @@
+use glyphon::{Attrs, Family, FontSystem, Metrics, TextArea, TextBounds, Weight};
+
+pub(crate) const PANEL_WIDTH: f32 = 320.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PanelKind {
+    Schema,
+    Profile(usize),
+    Tree { row: usize, col: usize },
+    Detail { row: usize, col: usize },
+    Notice,
+    Edit,
+}
+
+struct Entry {
+    top: f32,
+    height: f32,
+    buffer: glyphon::Buffer,
+}
+
+pub(crate) struct Panel {
+    pub(crate) kind: PanelKind,
+    scroll: f32,
+    entries: Vec<Entry>,
+}
+
+impl Panel {
+    pub(crate) fn new(
+        font_system: &mut FontSystem,
+        metrics: Metrics,
+        kind: PanelKind,
+        blocks: &[(String, String)],
+    ) -> Self {
+        // full values keep their layout, hex dumps and pretty printed json need it
+        let (body_attrs, wrap) = match kind {
+            PanelKind::Detail { .. } | PanelKind::Edit => {
+                (Attrs::new().family(Family::Monospace), glyphon::Wrap::Glyph)
+            }
+            _ => (Attrs::new(), glyphon::Wrap::Word),
+        };
+        let mut top = 0.;
+        let entries = blocks
+            .iter()
+            .map(|(title, body)| {
+                let mut buffer = glyphon::Buffer::new(font_system, metrics);
+                let mut buffer_bor = buffer.borrow_with(font_system);
+                buffer_bor.set_size(PANEL_WIDTH - 10., f32::MAX);
+                buffer_bor.set_wrap(wrap);
+                buffer_bor.set_rich_text(
+                    [
+                        (title.as_str(), Attrs::new().weight(Weight::BOLD)),
+                        (body.as_str(), body_attrs),
+                    ],
+                    glyphon::Shaping::Advanced,
+                );
+                let height = (buffer.layout_runs().count() + 1) as f32 * metrics.line_height;
+                let entry = Entry {
+                    top,
+                    height,
+                    buffer,
+                };
+                top += height;
+                entry
+            })
+            .collect();
+        Self {
+            kind,
+            scroll: 0.,
+            entries,
+        }
+    }
+
+    pub(crate) fn left(width: f32) -> f32 {
+        width - PANEL_WIDTH
+    }
+
+    pub(crate) fn contains(x: f32, width: f32) -> bool {
+        x >= Self::left(width)
+    }
+
+    pub(crate) fn scroll(&mut self, y_delta: f32, speed: f32) {
+        let content_height = self.entries.last().map_or(0., |e| e.top + e.height);
+        self.scroll += speed * y_delta * y_delta * y_delta.signum();
+        self.scroll = self.scroll.min(0.).max(-content_height);
+    }
+
+    pub(crate) fn entry_at(&self, y: f32) -> Option<usize> {
+        let y = y - self.scroll;
+        self.entries
+            .iter()
+            .position(|e| y >= e.top && y < e.top + e.height)
+    }
+
+    pub(crate) fn text_areas(
+        &self,
+        width: u32,
+        height: u32,
+        color: glyphon::Color,
+    ) -> Vec<TextArea<'_>> {
+        let left = Self::left(width as f32);
+        self.entries
+            .iter()
+            .map(|e| TextArea {
+                buffer: &e.buffer,
+                left: left + 10.,
+                top: self.scroll + e.top,
+                scale: 1.0,
+                bounds: TextBounds {
+                    left: left as i32,
+                    top: 0,
+                    right: width as i32,
+                    bottom: height as i32,
+                },
+                default_color: color,
+            })
+            .collect()
+    }
+}
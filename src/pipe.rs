@@ -0,0 +1,433 @@
+use std::{
+    any::Any,
+    fs::File,
+    io::{Cursor, Read},
+    path::Path,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{
+        csv, datatypes::SchemaRef, error::ArrowError, ipc::reader::StreamReader, json,
+        record_batch::RecordBatch,
+    },
+    datasource::{TableProvider, TableType},
+    error::Result,
+    execution::context::SessionState,
+    physical_plan::{memory::MemoryExec, ExecutionPlan},
+    prelude::*,
+};
+use tokio::sync::watch;
+
+use crate::source::{self, FileOptions};
+
+// bytes read from the pipe at a time
+const CHUNK: usize = 64 * 1024;
+// arrow ipc streams start with a continuation marker
+const IPC_MARKER: [u8; 4] = [0xff; 4];
+// how often a followed file is checked for new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+// rows read from a pipe so far, and whether it has ended
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Progress {
+    pub(crate) rows: usize,
+    pub(crate) done: bool,
+}
+
+// a pipe can only be read once, so its table outlives registering it again after a cast. A
+// followed file is read the same way, as a pipe that never ends
+struct Pipe {
+    path: String,
+    table: Arc<GrowingTable>,
+    progress: watch::Receiver<Progress>,
+}
+
+static PIPES: Mutex<Vec<Pipe>> = Mutex::new(vec![]);
+
+// `-` is stdin
+pub(crate) fn is_pipe(path: &str) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if std::fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo()) {
+            return true;
+        }
+    }
+    path == "-"
+}
+
+// csv and ndjson files are followed line by line, other formats can't be read half written
+pub(crate) fn is_followed(path: &str, options: &FileOptions) -> bool {
+    options.follow
+        && Path::new(path).is_file()
+        && matches!(
+            source::extension(path).as_str(),
+            "csv" | "tsv" | "json" | "ndjson" | "jsonl"
+        )
+}
+
+pub(crate) fn progress(path: &str) -> Option<watch::Receiver<Progress>> {
+    let pipes = PIPES.lock().unwrap();
+    let pipe = pipes.iter().find(|p| p.path == path)?;
+    Some(pipe.progress.clone())
+}
+
+// waits for more rows, forever once the pipe has ended
+pub(crate) async fn grown(progress: &mut Option<watch::Receiver<Progress>>) -> Progress {
+    let Some(rx) = progress else {
+        return std::future::pending().await;
+    };
+    // an error means the reader is gone, after saying it was done
+    rx.changed().await.ok();
+    let latest = *rx.borrow_and_update();
+    if latest.done {
+        *progress = None;
+    }
+    latest
+}
+
+pub(crate) fn register(
+    ctx: &SessionContext,
+    name: &str,
+    path: &str,
+    options: &FileOptions,
+) -> Result<()> {
+    let table = {
+        let mut pipes = PIPES.lock().unwrap();
+        match pipes.iter().find(|p| p.path == path) {
+            Some(pipe) => pipe.table.clone(),
+            None => {
+                let pipe = open(path, options)?;
+                let table = pipe.table.clone();
+                pipes.push(pipe);
+                table
+            }
+        }
+    };
+    ctx.deregister_table(name)?;
+    ctx.register_table(name, table)?;
+    Ok(())
+}
+
+enum Decoder {
+    Csv(Box<csv::reader::Decoder>),
+    Json(json::reader::Decoder),
+}
+
+impl Decoder {
+    fn decode(&mut self, buf: &[u8]) -> Result<usize, ArrowError> {
+        match self {
+            Self::Csv(decoder) => decoder.decode(buf),
+            Self::Json(decoder) => decoder.decode(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<Option<RecordBatch>, ArrowError> {
+        match self {
+            Self::Csv(decoder) => decoder.flush(),
+            Self::Json(decoder) => decoder.flush(),
+        }
+    }
+}
+
+// reads enough of the pipe to tell the format and the columns, and the rest in the background
+fn open(path: &str, options: &FileOptions) -> Result<Pipe> {
+    let follow = is_followed(path, options);
+    let mut input: Box<dyn Read + Send> = if path == "-" {
+        Box::new(std::io::stdin())
+    } else {
+        Box::new(File::open(path)?)
+    };
+    // a header and a row, or an ipc stream's marker
+    let mut head = vec![];
+    let mut chunk = vec![0; CHUNK];
+    while head.iter().filter(|b| **b == b'\n').count() < 2 && !head.starts_with(&IPC_MARKER) {
+        let n = input.read(&mut chunk)?;
+        // an empty followed file has no columns yet, wait for its first line
+        if n == 0 && follow && !head.contains(&b'\n') {
+            std::thread::sleep(FOLLOW_INTERVAL);
+            continue;
+        }
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&chunk[..n]);
+    }
+
+    let (progress_tx, progress) = watch::channel(Progress::default());
+    if head.starts_with(&IPC_MARKER) {
+        let reader = StreamReader::try_new(Cursor::new(head).chain(input), None)?;
+        let table = Arc::new(GrowingTable::new(reader.schema()));
+        let growing = table.clone();
+        std::thread::spawn(move || {
+            for batch in reader {
+                match batch {
+                    Ok(batch) => growing.push(batch, &progress_tx),
+                    Err(e) => {
+                        log::error!("could not read the arrow stream: {e}");
+                        break;
+                    }
+                }
+            }
+            progress_tx.send_modify(|p| p.done = true);
+        });
+        return Ok(Pipe {
+            path: path.to_string(),
+            table,
+            progress,
+        });
+    }
+
+    // types are inferred from the first lines, whatever arrived with them
+    let lines = match head.iter().rposition(|b| *b == b'\n') {
+        Some(end) => &head[..=end],
+        None => &head[..],
+    };
+    let is_json = head.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+    let (schema, decoder) = if is_json {
+        let (schema, _) = json::reader::infer_json_schema(lines, Some(options.infer_rows()))?;
+        let schema = Arc::new(schema);
+        let decoder = json::ReaderBuilder::new(schema.clone()).build_decoder()?;
+        (schema, Decoder::Json(decoder))
+    } else {
+        let delimiter = match source::extension(path).as_str() {
+            "tsv" => b'\t',
+            _ => b',',
+        };
+        let format = options.csv_format(delimiter);
+        let (schema, _) = format.infer_schema(lines, Some(options.infer_rows()))?;
+        let schema = Arc::new(schema);
+        let decoder = csv::ReaderBuilder::new(schema.clone())
+            .with_format(format)
+            .build_decoder();
+        (schema, Decoder::Csv(Box::new(decoder)))
+    };
+    let table = Arc::new(GrowingTable::new(schema));
+    let growing = table.clone();
+    let path_name = path.to_string();
+    std::thread::spawn(move || {
+        if let Err(e) = read_lines(input, head, decoder, follow, &growing, &progress_tx) {
+            log::error!("could not read {path_name}: {e}");
+        }
+        progress_tx.send_modify(|p| p.done = true);
+    });
+    Ok(Pipe {
+        path: path.to_string(),
+        table,
+        progress,
+    })
+}
+
+// decodes whole lines only, so rows show up as they arrive without splitting one. Reading a
+// followed file picks up where the last read ended, so only appended bytes are decoded
+fn read_lines(
+    mut input: impl Read,
+    mut pending: Vec<u8>,
+    mut decoder: Decoder,
+    follow: bool,
+    table: &GrowingTable,
+    progress: &watch::Sender<Progress>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut chunk = vec![0; CHUNK];
+    loop {
+        let end = pending.iter().rposition(|b| *b == b'\n').map(|i| i + 1);
+        if let Some(end) = end {
+            decode(&mut decoder, &pending[..end], table, progress)?;
+            pending.drain(..end);
+        }
+        let n = input.read(&mut chunk)?;
+        if n == 0 && follow {
+            std::thread::sleep(FOLLOW_INTERVAL);
+            continue;
+        }
+        if n == 0 {
+            // the last line may not end in a newline
+            if !pending.is_empty() {
+                pending.push(b'\n');
+                decode(&mut decoder, &pending, table, progress)?;
+            }
+            return Ok(());
+        }
+        pending.extend_from_slice(&chunk[..n]);
+    }
+}
+
+fn decode(
+    decoder: &mut Decoder,
+    mut buf: &[u8],
+    table: &GrowingTable,
+    progress: &watch::Sender<Progress>,
+) -> Result<(), ArrowError> {
+    while !buf.is_empty() {
+        // a full batch stops the decoder early
+        let n = decoder.decode(buf)?;
+        buf = &buf[n..];
+        match decoder.flush()? {
+            Some(batch) => table.push(batch, progress),
+            None if n == 0 => break,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+// batches appended while the pipe is read, each scan sees the rows read so far
+#[derive(Debug)]
+struct GrowingTable {
+    schema: SchemaRef,
+    batches: RwLock<Vec<RecordBatch>>,
+}
+
+impl GrowingTable {
+    fn new(schema: SchemaRef) -> Self {
+        Self {
+            schema,
+            batches: RwLock::new(vec![]),
+        }
+    }
+
+    fn push(&self, batch: RecordBatch, progress: &watch::Sender<Progress>) {
+        if batch.num_rows() == 0 {
+            return;
+        }
+        let rows = batch.num_rows();
+        self.batches.write().unwrap().push(batch);
+        progress.send_modify(|p| p.rows += rows);
+    }
+}
+
+#[async_trait]
+impl TableProvider for GrowingTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        _state: &SessionState,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let batches = self.batches.read().unwrap().clone();
+        Ok(Arc::new(MemoryExec::try_new(
+            &[batches],
+            self.schema.clone(),
+            projection.cloned(),
+        )?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::{
+        array::{AsArray, Int64Array},
+        datatypes::{DataType, Field, Int64Type, Schema},
+        ipc::writer::StreamWriter,
+    };
+    use serde_json::Value;
+
+    use super::*;
+
+    // a file of its own for each test, read the way a pipe is
+    async fn read(name: &str, bytes: &[u8]) -> Pipe {
+        let file = std::env::temp_dir().join(format!("raam-pipe-{}-{name}", std::process::id()));
+        std::fs::write(&file, bytes).unwrap();
+        let pipe = open(
+            &file.to_string_lossy(),
+            &FileOptions::from_json(&Value::Null),
+        )
+        .unwrap();
+        let mut progress = Some(pipe.progress.clone());
+        while !grown(&mut progress).await.done {}
+        std::fs::remove_file(file).unwrap();
+        pipe
+    }
+
+    fn column(table: &GrowingTable, i: usize) -> Vec<i64> {
+        let batches = table.batches.read().unwrap();
+        batches
+            .iter()
+            .flat_map(|b| b.column(i).as_primitive::<Int64Type>().values().to_vec())
+            .collect()
+    }
+
+    // hands out a few bytes at a time, as a slow writer would
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[tokio::test]
+    async fn csv_is_read_to_the_last_line_without_a_newline() {
+        let pipe = read("last.csv", b"a,b\n1,2\n3,4\n5,6").await;
+        assert_eq!(pipe.progress.borrow().rows, 3);
+        assert_eq!(column(&pipe.table, 0), [1, 3, 5]);
+        assert_eq!(column(&pipe.table, 1), [2, 4, 6]);
+    }
+
+    #[tokio::test]
+    async fn json_lines_are_told_by_their_brace() {
+        let pipe = read("lines.ndjson", b"{\"a\": 1}\n{\"a\": 2}\n\n{\"a\": 3}\n").await;
+        assert_eq!(pipe.table.schema.field(0).data_type(), &DataType::Int64);
+        assert_eq!(column(&pipe.table, 0), [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn arrow_streams_are_told_by_their_marker() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, false)]));
+        let mut bytes = vec![];
+        let mut writer = StreamWriter::try_new(&mut bytes, &schema).unwrap();
+        for values in [vec![1, 2], vec![3]] {
+            let array = Arc::new(Int64Array::from(values));
+            writer
+                .write(&RecordBatch::try_new(schema.clone(), vec![array]).unwrap())
+                .unwrap();
+        }
+        writer.finish().unwrap();
+        drop(writer);
+        let pipe = read("stream.arrow", &bytes).await;
+        assert_eq!(pipe.progress.borrow().rows, 3);
+        assert_eq!(column(&pipe.table, 0), [1, 2, 3]);
+    }
+
+    #[test]
+    fn lines_split_across_reads_are_decoded_whole() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, true)]));
+        let format = FileOptions::from_json(&Value::Null).csv_format(b',');
+        let decoder = csv::ReaderBuilder::new(schema.clone())
+            .with_format(format)
+            .build_decoder();
+        let table = GrowingTable::new(schema);
+        let (progress, _) = watch::channel(Progress::default());
+        let input = Trickle(b"123\n4567\n89");
+        read_lines(
+            input,
+            b"a\n".to_vec(),
+            Decoder::Csv(Box::new(decoder)),
+            false,
+            &table,
+            &progress,
+        )
+        .unwrap();
+        assert_eq!(column(&table, 0), [123, 4567, 89]);
+        assert_eq!(progress.borrow().rows, 3);
+    }
+}
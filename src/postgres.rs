@@ -0,0 +1,821 @@
+use std::{any::Any, fmt, sync::Arc};
+
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{
+        datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
+        record_batch::{RecordBatch, RecordBatchOptions},
+    },
+    common::{
+        plan_err,
+        tree_node::{Transformed, TreeNode},
+        ScalarValue,
+    },
+    config::ConfigOptions,
+    datasource::{TableProvider, TableType},
+    error::{DataFusionError, Result},
+    execution::{context::SessionState, TaskContext},
+    logical_expr::{
+        expr::{InList, Like},
+        BinaryExpr, Cast, Operator, TableProviderFilterPushDown,
+    },
+    physical_expr::{expressions::Column, PhysicalSortExpr},
+    physical_optimizer::PhysicalOptimizerRule,
+    physical_plan::{
+        limit::GlobalLimitExec, projection::ProjectionExec, sorts::sort::SortExec,
+        stream::RecordBatchStreamAdapter, DisplayAs, DisplayFormatType, ExecutionPlan,
+        Partitioning, SendableRecordBatchStream,
+    },
+    prelude::*,
+};
+use futures::{StreamExt, TryStreamExt};
+use tokio_postgres::{Client, NoTls, Row};
+
+use crate::database::{external, quote, Builder, DatabaseExec, Value};
+
+// rows per batch streamed from the server
+const BATCH_ROWS: usize = 8192;
+
+pub(crate) fn is_url(path: &str) -> bool {
+    path.starts_with("postgres://") || path.starts_with("postgresql://")
+}
+
+// `postgres://host/shop#orders` is one table of the database, the url on its own its first
+// table. Tables outside the public schema are named `schema.table`
+pub(crate) fn split(path: &str) -> Option<(&str, Option<&str>)> {
+    if !is_url(path) {
+        return None;
+    }
+    match path.rsplit_once('#') {
+        Some((url, table)) => Some((url, Some(table))),
+        None => Some((path, None)),
+    }
+}
+
+async fn connect(url: &str) -> Result<Client> {
+    let (client, connection) = tokio_postgres::connect(url, NoTls)
+        .await
+        .map_err(external)?;
+    // the connection does the talking to the server, and ends when the client is dropped
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            log::error!("postgres connection failed: {e}");
+        }
+    });
+    Ok(client)
+}
+
+// every table of a database as a url of its own, any other url as it is
+pub(crate) async fn expand(path: &str) -> Vec<String> {
+    let Some((url, None)) = split(path) else {
+        return vec![path.to_string()];
+    };
+    match tables(url).await {
+        Ok(tables) if !tables.is_empty() => tables.iter().map(|t| format!("{url}#{t}")).collect(),
+        Ok(_) => vec![path.to_string()],
+        Err(e) => {
+            log::error!("could not list the tables of {url}: {e}");
+            vec![path.to_string()]
+        }
+    }
+}
+
+async fn tables(url: &str) -> Result<Vec<String>> {
+    let client = connect(url).await?;
+    let rows = client
+        .query(
+            "SELECT table_schema, table_name FROM information_schema.tables \
+             WHERE table_schema NOT IN ('pg_catalog', 'information_schema') \
+             ORDER BY table_schema <> 'public', table_schema, table_name",
+            &[],
+        )
+        .await
+        .map_err(external)?;
+    Ok(rows
+        .iter()
+        .map(|row| match (row.get(0), row.get::<_, String>(1)) {
+            ("public", table) => table,
+            (schema, table) => format!("{schema}.{table}"),
+        })
+        .collect())
+}
+
+// the columns of a table's primary key, none for a view or a table without one
+pub(crate) async fn primary_key(path: &str) -> Result<Vec<String>> {
+    let Some((url, table)) = split(path) else {
+        return Ok(vec![]);
+    };
+    let table = match table {
+        Some(table) => table.to_string(),
+        None => match tables(url).await?.into_iter().next() {
+            Some(table) => table,
+            None => return Ok(vec![]),
+        },
+    };
+    let client = connect(url).await?;
+    let (schema_name, table_name) = schema_and_table(&table);
+    key(
+        &client,
+        &format!("{}.{}", quote(schema_name), quote(table_name)),
+    )
+    .await
+}
+
+async fn key(client: &Client, table: &str) -> Result<Vec<String>> {
+    Ok(client
+        .query(
+            "SELECT a.attname FROM pg_index i \
+             JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey) \
+             WHERE i.indrelid = CAST($1 AS text)::regclass AND i.indisprimary \
+             ORDER BY array_position(CAST(i.indkey AS int2[]), a.attnum)",
+            &[&table],
+        )
+        .await
+        .map_err(external)?
+        .iter()
+        .map(|row| row.get(0))
+        .collect())
+}
+
+fn schema_and_table(table: &str) -> (&str, &str) {
+    table.split_once('.').unwrap_or(("public", table))
+}
+
+// postgres types as the arrow types the grid knows, anything else is shown as text
+fn arrow_type(declared: &str) -> DataType {
+    match declared {
+        "smallint" | "integer" | "bigint" => DataType::Int64,
+        "real" | "double precision" | "numeric" => DataType::Float64,
+        "boolean" => DataType::Boolean,
+        "date" => DataType::Date32,
+        "bytea" => DataType::Binary,
+        t if t.starts_with("timestamp") => DataType::Timestamp(TimeUnit::Microsecond, None),
+        _ => DataType::Utf8,
+    }
+}
+
+// the server converts each column to what the grid reads, so a filter or sort on the
+// expression compares the same values datafusion would
+fn select_expr(name: &str, data_type: &DataType) -> String {
+    let column = quote(name);
+    match data_type {
+        DataType::Int64 => format!("CAST({column} AS bigint)"),
+        DataType::Float64 => format!("CAST({column} AS double precision)"),
+        DataType::Date32 => format!("CAST({column} - DATE '1970-01-01' AS bigint)"),
+        DataType::Timestamp(..) => {
+            format!("CAST(extract(epoch FROM {column}) * 1000000 AS bigint)")
+        }
+        DataType::Utf8 => format!("CAST({column} AS text)"),
+        _ => column,
+    }
+}
+
+pub(crate) async fn register(
+    ctx: &SessionContext,
+    name: &str,
+    url: &str,
+    table: Option<&str>,
+) -> Result<()> {
+    let table = match table {
+        Some(table) => table.to_string(),
+        None => match tables(url).await?.into_iter().next() {
+            Some(table) => table,
+            None => return plan_err!("{url} has no tables"),
+        },
+    };
+    let client = connect(url).await?;
+    let (schema_name, table_name) = schema_and_table(&table);
+    let rows = client
+        .query(
+            "SELECT column_name, data_type FROM information_schema.columns \
+             WHERE table_schema = $1 AND table_name = $2 ORDER BY ordinal_position",
+            &[&schema_name, &table_name],
+        )
+        .await
+        .map_err(external)?;
+    let fields: Vec<_> = rows
+        .iter()
+        .map(|row| Field::new(row.get::<_, String>(0), arrow_type(row.get(1)), true))
+        .collect();
+    if fields.is_empty() {
+        return plan_err!("no table {table} in {url}");
+    }
+    let table = format!("{}.{}", quote(schema_name), quote(table_name));
+    let key = key(&client, &table).await?;
+    let provider = PostgresTable {
+        client: Arc::new(client),
+        table,
+        key,
+        schema: Arc::new(Schema::new(fields)),
+    };
+    ctx.deregister_table(name)?;
+    ctx.register_table(name, Arc::new(provider))?;
+    Ok(())
+}
+
+// a table on the server, which does the projection, filtering, sorting and paging itself
+struct PostgresTable {
+    client: Arc<Client>,
+    table: String,
+    // primary key columns, the order pages are read in
+    key: Vec<String>,
+    schema: SchemaRef,
+}
+
+// a filter as sql on the converted columns, if the server can evaluate it the same way
+fn sql(schema: &Schema, expr: &Expr) -> Option<String> {
+    let sql = |expr: &Expr| sql(schema, expr);
+    match expr {
+        Expr::Column(column) => {
+            let field = schema.field_with_name(&column.name).ok()?;
+            Some(select_expr(field.name(), field.data_type()))
+        }
+        Expr::Literal(value) => literal(value),
+        Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+            let op = match op {
+                Operator::Eq => "=",
+                Operator::NotEq => "<>",
+                Operator::Lt => "<",
+                Operator::LtEq => "<=",
+                Operator::Gt => ">",
+                Operator::GtEq => ">=",
+                Operator::And => "AND",
+                Operator::Or => "OR",
+                _ => return None,
+            };
+            Some(format!("({} {op} {})", sql(left)?, sql(right)?))
+        }
+        Expr::Not(expr) => Some(format!("(NOT {})", sql(expr)?)),
+        Expr::IsNull(expr) => Some(format!("({} IS NULL)", sql(expr)?)),
+        Expr::IsNotNull(expr) => Some(format!("({} IS NOT NULL)", sql(expr)?)),
+        Expr::Like(Like {
+            negated,
+            expr,
+            pattern,
+            escape_char: None,
+            case_insensitive,
+        }) => {
+            let not = if *negated { "NOT " } else { "" };
+            let like = if *case_insensitive { "ILIKE" } else { "LIKE" };
+            Some(format!("({} {not}{like} {})", sql(expr)?, sql(pattern)?))
+        }
+        Expr::InList(InList {
+            expr,
+            list,
+            negated,
+        }) => {
+            let list: Option<Vec<_>> = list.iter().map(sql).collect();
+            let not = if *negated { "NOT " } else { "" };
+            Some(format!("({} {not}IN ({}))", sql(expr)?, list?.join(", ")))
+        }
+        // only casts that give the same numbers on both sides, postgres rounds floats to
+        // integers where datafusion truncates, and formats dates as text its own way
+        Expr::Cast(Cast { expr, data_type }) => {
+            let Expr::Column(column) = expr.as_ref() else {
+                return None;
+            };
+            let from = schema.field_with_name(&column.name).ok()?.data_type();
+            let target = match (from, data_type) {
+                (DataType::Int64, DataType::Float64) => "double precision",
+                (DataType::Date32 | DataType::Timestamp(..), DataType::Int64) => "bigint",
+                _ => return None,
+            };
+            Some(format!("CAST({} AS {target})", sql(expr)?))
+        }
+        _ => None,
+    }
+}
+
+fn literal(value: &ScalarValue) -> Option<String> {
+    match value {
+        ScalarValue::Boolean(Some(v)) => Some(v.to_string()),
+        ScalarValue::Int8(Some(v)) => Some(v.to_string()),
+        ScalarValue::Int16(Some(v)) => Some(v.to_string()),
+        ScalarValue::Int32(Some(v)) => Some(v.to_string()),
+        ScalarValue::Int64(Some(v)) => Some(v.to_string()),
+        ScalarValue::UInt8(Some(v)) => Some(v.to_string()),
+        ScalarValue::UInt16(Some(v)) => Some(v.to_string()),
+        ScalarValue::UInt32(Some(v)) => Some(v.to_string()),
+        ScalarValue::UInt64(Some(v)) => Some(v.to_string()),
+        ScalarValue::Float32(Some(v)) if v.is_finite() => Some(format!("{v:?}")),
+        ScalarValue::Float64(Some(v)) if v.is_finite() => Some(format!("{v:?}")),
+        // compared byte by byte, as datafusion does, rather than in the server's locale
+        ScalarValue::Utf8(Some(v)) | ScalarValue::LargeUtf8(Some(v)) => {
+            Some(format!("'{}' COLLATE \"C\"", v.replace('\'', "''")))
+        }
+        ScalarValue::Date32(Some(v)) => Some(v.to_string()),
+        ScalarValue::TimestampMicrosecond(Some(v), None) => Some(v.to_string()),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl TableProvider for PostgresTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        Ok(filters
+            .iter()
+            .map(|filter| match sql(&self.schema, filter) {
+                Some(_) => TableProviderFilterPushDown::Exact,
+                None => TableProviderFilterPushDown::Unsupported,
+            })
+            .collect())
+    }
+
+    async fn scan(
+        &self,
+        _state: &SessionState,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let schema = match projection {
+            Some(projection) => Arc::new(self.schema.project(projection)?),
+            None => self.schema.clone(),
+        };
+        Ok(Arc::new(PostgresExec {
+            client: self.client.clone(),
+            select: Select {
+                table: self.table.clone(),
+                key: self.key.clone(),
+                schema,
+                filters: filters
+                    .iter()
+                    .filter_map(|f| sql(&self.schema, f))
+                    .collect(),
+                order: vec![],
+                offset: 0,
+                limit,
+            },
+        }))
+    }
+}
+
+// one select on the server, rewritten by `PushDown` to sort and skip rows there as well
+#[derive(Clone)]
+struct PostgresExec {
+    client: Arc<Client>,
+    select: Select,
+}
+
+// what the select reads, as the scan and `PushDown` left it
+#[derive(Debug, Clone)]
+struct Select {
+    table: String,
+    key: Vec<String>,
+    schema: SchemaRef,
+    filters: Vec<String>,
+    order: Vec<PhysicalSortExpr>,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl Select {
+    fn sql(&self) -> String {
+        let columns: Vec<_> = self
+            .schema
+            .fields()
+            .iter()
+            .map(|f| select_expr(f.name(), f.data_type()))
+            .collect();
+        // counting rows needs no columns, but a select does
+        let columns = if columns.is_empty() {
+            "1".to_string()
+        } else {
+            columns.join(", ")
+        };
+        let mut sql = format!("SELECT {columns} FROM {}", self.table);
+        if !self.filters.is_empty() {
+            sql.push_str(&format!(" WHERE {}", self.filters.join(" AND ")));
+        }
+        if !self.order.is_empty() {
+            let order: Vec<_> = self
+                .order
+                .iter()
+                .map(|sort| {
+                    // only plain columns are pushed down
+                    let column = sort.expr.as_any().downcast_ref::<Column>().unwrap();
+                    let field = self.schema.field(column.index());
+                    let collate = match field.data_type() {
+                        DataType::Utf8 => " COLLATE \"C\"",
+                        _ => "",
+                    };
+                    let direction = if sort.options.descending {
+                        "DESC"
+                    } else {
+                        "ASC"
+                    };
+                    let nulls = if sort.options.nulls_first {
+                        "FIRST"
+                    } else {
+                        "LAST"
+                    };
+                    let column = select_expr(field.name(), field.data_type());
+                    format!("{column}{collate} {direction} NULLS {nulls}")
+                })
+                .collect();
+            sql.push_str(&format!(" ORDER BY {}", order.join(", ")));
+        } else if self.limit.is_some() || self.offset > 0 {
+            // without an order the server may return the rows of each page in any order
+            let key: Vec<_> = self.key.iter().map(|k| quote(k)).collect();
+            if !key.is_empty() {
+                sql.push_str(&format!(" ORDER BY {}", key.join(", ")));
+            }
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {limit}"));
+        }
+        if self.offset > 0 {
+            sql.push_str(&format!(" OFFSET {}", self.offset));
+        }
+        sql
+    }
+}
+
+impl fmt::Debug for PostgresExec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PostgresExec: {}", self.select.sql())
+    }
+}
+
+impl DisplayAs for PostgresExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PostgresExec: {}", self.select.sql())
+    }
+}
+
+impl ExecutionPlan for PostgresExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.select.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(1)
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        if self.select.order.is_empty() {
+            None
+        } else {
+            Some(&self.select.order)
+        }
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        _partition: usize,
+        _context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let (client, sql) = (self.client.clone(), self.select.sql());
+        let schema = self.select.schema.clone();
+        log::debug!("{sql}");
+        let batch_schema = schema.clone();
+        let batches = futures::stream::once(async move {
+            let rows = client
+                .query_raw(&sql, std::iter::empty::<i64>())
+                .await
+                .map_err(external)?;
+            Ok::<_, DataFusionError>(rows.map_err(external))
+        })
+        .try_flatten()
+        .chunks(BATCH_ROWS)
+        .map(move |rows| {
+            let rows = rows.into_iter().collect::<Result<Vec<_>>>()?;
+            batch(&batch_schema, &rows)
+        });
+        Ok(Box::pin(RecordBatchStreamAdapter::new(schema, batches)))
+    }
+}
+
+fn batch(schema: &SchemaRef, rows: &[Row]) -> Result<RecordBatch> {
+    let mut builders: Vec<_> = schema
+        .fields()
+        .iter()
+        .map(|f| Builder::new(f.data_type()))
+        .collect();
+    for row in rows {
+        for (i, (builder, field)) in builders.iter_mut().zip(schema.fields()).enumerate() {
+            builder.append(value(row, i, field.data_type()).map_err(external)?)?;
+        }
+    }
+    let arrays = builders.iter_mut().map(Builder::finish).collect();
+    let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+    Ok(RecordBatch::try_new_with_options(
+        schema.clone(),
+        arrays,
+        &options,
+    )?)
+}
+
+fn value(row: &Row, i: usize, data_type: &DataType) -> Result<Value, tokio_postgres::Error> {
+    Ok(match data_type {
+        DataType::Int64 | DataType::Date32 | DataType::Timestamp(..) => row
+            .try_get::<_, Option<i64>>(i)?
+            .map_or(Value::Null, Value::Int),
+        DataType::Float64 => row
+            .try_get::<_, Option<f64>>(i)?
+            .map_or(Value::Null, Value::Real),
+        DataType::Boolean => row
+            .try_get::<_, Option<bool>>(i)?
+            .map_or(Value::Null, Value::Bool),
+        DataType::Binary => row
+            .try_get::<_, Option<Vec<u8>>>(i)?
+            .map_or(Value::Null, Value::Blob),
+        _ => row
+            .try_get::<_, Option<String>>(i)?
+            .map_or(Value::Null, Value::Text),
+    })
+}
+
+// datafusion hands a table scan only its projection, filters and limit, this moves sorts and
+// skipped rows above a postgres scan into its query, so paging deep into a table reads a
+// page from the server rather than every row before it. Skipped rows above a sqlite or
+// duckdb scan go into its query as well
+#[derive(Debug)]
+pub(crate) struct PushDown;
+
+impl PhysicalOptimizerRule for PushDown {
+    fn optimize(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+        _config: &ConfigOptions,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        plan.transform_up(&|plan| {
+            if let Some(sort) = plan.as_any().downcast_ref::<SortExec>() {
+                if let Some(exec) = push_sort(sort) {
+                    return Ok(Transformed::Yes(exec));
+                }
+            }
+            if let Some(limit) = plan.as_any().downcast_ref::<GlobalLimitExec>() {
+                if limit.skip() > 0 {
+                    if let Some(input) = push_offset(limit.input(), limit.skip()) {
+                        let limit = GlobalLimitExec::new(input, 0, limit.fetch());
+                        return Ok(Transformed::Yes(Arc::new(limit)));
+                    }
+                }
+            }
+            Ok(Transformed::No(plan))
+        })
+    }
+
+    fn name(&self) -> &str {
+        "postgres_push_down"
+    }
+
+    fn schema_check(&self) -> bool {
+        true
+    }
+}
+
+fn push_sort(sort: &SortExec) -> Option<Arc<dyn ExecutionPlan>> {
+    let exec = sort.input().as_any().downcast_ref::<PostgresExec>()?;
+    let select = &exec.select;
+    let plain = sort
+        .expr()
+        .iter()
+        .all(|e| e.expr.as_any().downcast_ref::<Column>().is_some());
+    // a limit on the scan takes rows before they are sorted, the server can't do it after
+    if !plain || !select.order.is_empty() || select.offset > 0 || select.limit.is_some() {
+        return None;
+    }
+    Some(Arc::new(PostgresExec {
+        client: exec.client.clone(),
+        select: Select {
+            order: sort.expr().to_vec(),
+            limit: sort.fetch(),
+            ..select.clone()
+        },
+    }))
+}
+
+// skips rows on the server, through projections since they keep every row
+fn push_offset(plan: &Arc<dyn ExecutionPlan>, skip: usize) -> Option<Arc<dyn ExecutionPlan>> {
+    if let Some(exec) = plan.as_any().downcast_ref::<DatabaseExec>() {
+        return Some(Arc::new(exec.skip(skip)));
+    }
+    if let Some(exec) = plan.as_any().downcast_ref::<PostgresExec>() {
+        let select = &exec.select;
+        return Some(Arc::new(PostgresExec {
+            client: exec.client.clone(),
+            select: Select {
+                offset: select.offset + skip,
+                limit: select.limit.map(|limit| limit.saturating_sub(skip)),
+                ..select.clone()
+            },
+        }));
+    }
+    let projection = plan.as_any().downcast_ref::<ProjectionExec>()?;
+    let input = push_offset(projection.input(), skip)?;
+    plan.clone().with_new_children(vec![input]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        arrow::{array::AsArray, compute::SortOptions},
+        physical_plan::{collect, displayable},
+    };
+
+    use super::*;
+    use crate::source;
+
+    fn schema() -> Schema {
+        Schema::new(vec![
+            Field::new("id", DataType::Int64, true),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("day", DataType::Date32, true),
+            Field::new("price", DataType::Float64, true),
+        ])
+    }
+
+    fn select(fields: &[usize]) -> Select {
+        Select {
+            table: "\"public\".\"orders\"".to_string(),
+            key: vec!["id".to_string()],
+            schema: Arc::new(schema().project(fields).unwrap()),
+            filters: vec![],
+            order: vec![],
+            offset: 0,
+            limit: None,
+        }
+    }
+
+    #[test]
+    fn filters_as_sql() {
+        let schema = schema();
+        let sql = |expr: Expr| sql(&schema, &expr);
+        assert_eq!(
+            sql(col("id").gt(lit(3i64)).and(col("name").eq(lit("it's")))).unwrap(),
+            "((CAST(\"id\" AS bigint) > 3) AND (CAST(\"name\" AS text) = 'it''s' COLLATE \"C\"))"
+        );
+        assert_eq!(
+            sql(col("name").like(lit("a%")).not()).unwrap(),
+            "(CAST(\"name\" AS text) NOT LIKE 'a%' COLLATE \"C\")"
+        );
+        assert_eq!(
+            sql(col("id").in_list(vec![lit(1i64), lit(2i64)], true)).unwrap(),
+            "(CAST(\"id\" AS bigint) NOT IN (1, 2))"
+        );
+        assert_eq!(
+            sql(col("price").is_null().or(col("price").lt_eq(lit(2.5)))).unwrap(),
+            "((CAST(\"price\" AS double precision) IS NULL) OR \
+             (CAST(\"price\" AS double precision) <= 2.5))"
+        );
+        assert_eq!(
+            sql(cast(col("id"), DataType::Float64).gt(lit(1.0))).unwrap(),
+            "(CAST(CAST(\"id\" AS bigint) AS double precision) > 1.0)"
+        );
+    }
+
+    #[test]
+    fn filters_the_server_would_evaluate_differently_stay_in_datafusion() {
+        let schema = schema();
+        let sql = |expr: Expr| sql(&schema, &expr);
+        // postgres rounds where datafusion truncates
+        assert_eq!(sql(cast(col("price"), DataType::Int64).eq(lit(1i64))), None);
+        assert_eq!(sql(cast(col("day"), DataType::Utf8).eq(lit("x"))), None);
+        assert_eq!(sql(col("price").gt(lit(f64::NAN))), None);
+        assert_eq!(sql((col("id") + lit(1i64)).gt(lit(3i64))), None);
+        assert_eq!(sql(col("missing").is_null()), None);
+    }
+
+    #[test]
+    fn selects_the_projected_columns() {
+        assert_eq!(
+            select(&[0, 2]).sql(),
+            "SELECT CAST(\"id\" AS bigint), CAST(\"day\" - DATE '1970-01-01' AS bigint) \
+             FROM \"public\".\"orders\""
+        );
+        assert_eq!(select(&[]).sql(), "SELECT 1 FROM \"public\".\"orders\"");
+    }
+
+    #[test]
+    fn pages_in_key_order_without_a_sort() {
+        let select = Select {
+            filters: vec!["(CAST(\"id\" AS bigint) > 3)".to_string()],
+            offset: 200,
+            limit: Some(100),
+            ..select(&[1])
+        };
+        assert_eq!(
+            select.sql(),
+            "SELECT CAST(\"name\" AS text) FROM \"public\".\"orders\" \
+             WHERE (CAST(\"id\" AS bigint) > 3) ORDER BY \"id\" LIMIT 100 OFFSET 200"
+        );
+    }
+
+    #[test]
+    fn sorts_on_the_server() {
+        let sort = |name, index, descending, nulls_first| PhysicalSortExpr {
+            expr: Arc::new(Column::new(name, index)),
+            options: SortOptions {
+                descending,
+                nulls_first,
+            },
+        };
+        let select = Select {
+            order: vec![sort("name", 1, true, true), sort("price", 2, false, false)],
+            limit: Some(10),
+            ..select(&[0, 1, 3])
+        };
+        assert!(select.sql().ends_with(
+            "ORDER BY CAST(\"name\" AS text) COLLATE \"C\" DESC NULLS FIRST, \
+             CAST(\"price\" AS double precision) ASC NULLS LAST LIMIT 10"
+        ));
+    }
+
+    // runs against the server in `DATABASE_URL`, and is skipped without one
+    #[tokio::test]
+    async fn push_down_pages_on_the_server() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL is not set, skipping the postgres test");
+            return;
+        };
+        let table = format!("raam_push_down_{}", std::process::id());
+        let client = connect(&url).await.unwrap();
+        client
+            .batch_execute(&format!(
+                "CREATE TABLE {table} (id integer PRIMARY KEY, name text, amount real); \
+                 INSERT INTO {table} SELECT i, 'n' || i, i * 1.5 FROM generate_series(0, 99) i"
+            ))
+            .await
+            .unwrap();
+
+        let ctx = source::context();
+        let run = |query: &'static str| {
+            let ctx = ctx.clone();
+            async move {
+                let plan = ctx.sql(query).await?.create_physical_plan().await?;
+                let shown = displayable(plan.as_ref()).indent(true).to_string();
+                let batches = collect(plan, ctx.task_ctx()).await?;
+                let names: Vec<_> = batches
+                    .iter()
+                    .flat_map(|b| b.column(0).as_string::<i32>().iter())
+                    .map(|name| name.unwrap_or_default().to_string())
+                    .collect();
+                Ok::<_, DataFusionError>((shown, names))
+            }
+        };
+        let results = async {
+            register(&ctx, "t", &url, Some(&table)).await?;
+            let sorted =
+                run("SELECT name FROM t WHERE id >= 10 ORDER BY amount DESC LIMIT 5 OFFSET 20")
+                    .await?;
+            let paged = run("SELECT name FROM t LIMIT 3 OFFSET 40").await?;
+            let key = primary_key(&format!("{url}#{table}")).await?;
+            Ok::<_, DataFusionError>((sorted, paged, key))
+        }
+        .await;
+        client
+            .batch_execute(&format!("DROP TABLE {table}"))
+            .await
+            .unwrap();
+        let ((sorted_plan, sorted), (paged_plan, paged), key) = results.unwrap();
+
+        assert!(!sorted_plan.contains("SortExec"), "{sorted_plan}");
+        assert!(
+            sorted_plan.contains(
+                "WHERE (CAST(\"id\" AS bigint) >= 10) \
+                 ORDER BY CAST(\"amount\" AS double precision) DESC NULLS FIRST LIMIT 5 OFFSET 20"
+            ),
+            "{sorted_plan}"
+        );
+        assert_eq!(sorted, ["n79", "n78", "n77", "n76", "n75"]);
+
+        assert!(
+            paged_plan.contains("ORDER BY \"id\" LIMIT 3 OFFSET 40"),
+            "{paged_plan}"
+        );
+        assert_eq!(paged, ["n40", "n41", "n42"]);
+        assert_eq!(key, ["id"]);
+    }
+}
@@ -0,0 +1,136 @@
+use datafusion::{
+    arrow::{
+        datatypes::DataType,
+        record_batch::RecordBatch,
+        util::display::{ArrayFormatter, FormatOptions},
+    },
+    common::plan_err,
+    error::{DataFusionError, Result},
+    prelude::*,
+};
+
+const PERCENTILES: [f64; 5] = [0.05, 0.25, 0.5, 0.75, 0.95];
+const TOP_K: usize = 10;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Profile {
+    pub(crate) column: usize,
+    pub(crate) name: String,
+    pub(crate) stats: Vec<(String, String)>,
+    pub(crate) top_values: Vec<(String, String)>,
+}
+
+impl Profile {
+    pub(crate) async fn compute(df: DataFrame, column: usize) -> Result<Self> {
+        // the columns may have changed since the profile was asked for
+        let Some(field) = df.schema().fields().get(column).cloned() else {
+            return plan_err!("there is no column {}", column + 1);
+        };
+        let name = field.name().clone();
+        let data_type = field.data_type();
+        let c = || ident(&name);
+
+        let mut aggregates = vec![
+            ("rows".to_string(), count(lit(1))),
+            (
+                "nulls".to_string(),
+                sum(cast(c().is_null(), DataType::Int64)),
+            ),
+        ];
+        // approx_distinct only has HyperLogLog support for integer and string types
+        if data_type.is_integer()
+            || matches!(
+                data_type,
+                DataType::Utf8 | DataType::LargeUtf8 | DataType::Binary | DataType::LargeBinary
+            )
+        {
+            aggregates.push(("distinct (approx)".to_string(), approx_distinct(c())));
+        } else {
+            aggregates.push(("distinct".to_string(), count_distinct(c())));
+        }
+        if !data_type.is_nested() {
+            aggregates.push(("min".to_string(), min(c())));
+            aggregates.push(("max".to_string(), max(c())));
+        }
+        if data_type.is_numeric() {
+            aggregates.push(("mean".to_string(), avg(c())));
+            aggregates.push(("stddev".to_string(), stddev(c())));
+            for p in PERCENTILES {
+                aggregates.push((
+                    format!("p{}", (p * 100.) as usize),
+                    approx_percentile_cont(c(), lit(p)),
+                ));
+            }
+        }
+
+        let (labels, exprs): (Vec<_>, Vec<_>) = aggregates
+            .into_iter()
+            .enumerate()
+            .map(|(i, (label, expr))| (label, expr.alias(format!("stat_{i}"))))
+            .unzip();
+        let batches = df.clone().aggregate(vec![], exprs)?.collect().await?;
+        let values = batch_rows(&batches)?.into_iter().next().unwrap_or_default();
+        let stats = labels.into_iter().zip(values).collect();
+
+        let top_values = df
+            .aggregate(vec![c()], vec![count(lit(1)).alias("n")])?
+            .sort(vec![col("n").sort(false, false)])?
+            .limit(0, Some(TOP_K))?
+            .collect()
+            .await?;
+        let top_values = batch_rows(&top_values)?
+            .into_iter()
+            .map(|row| (row[0].clone(), row[1].clone()))
+            .collect();
+
+        Ok(Self {
+            column,
+            name,
+            stats,
+            top_values,
+        })
+    }
+
+    pub(crate) fn blocks(&self) -> Vec<(String, String)> {
+        let stats = self
+            .stats
+            .iter()
+            .map(|(label, value)| format!("\n  {label}: {value}"))
+            .collect();
+        let top_values = self
+            .top_values
+            .iter()
+            .map(|(value, n)| format!("\n  {value}: {n}"))
+            .collect();
+        vec![
+            (self.name.clone(), stats),
+            ("top values".to_string(), top_values),
+        ]
+    }
+}
+
+// in place of the statistics, when they couldn't be computed
+pub(crate) fn failed_blocks(name: &str, error: &str) -> Vec<(String, String)> {
+    vec![(name.to_string(), format!("\n  could not compute: {error}"))]
+}
+
+fn batch_rows(batches: &[RecordBatch]) -> Result<Vec<Vec<String>>> {
+    let format_options = FormatOptions::default().with_null("null");
+    let mut rows = vec![];
+    for batch in batches {
+        let formatters = batch
+            .columns()
+            .iter()
+            .map(|c| ArrayFormatter::try_new(c.as_ref(), &format_options))
+            .collect::<Result<Vec<_>, _>>()?;
+        for row in 0..batch.num_rows() {
+            rows.push(
+                formatters
+                    .iter()
+                    .map(|f| f.value(row).to_string())
+                    .collect(),
+            );
+        }
+    }
+    Ok(rows)
+}
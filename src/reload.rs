@@ -0,0 +1,70 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    database, pipe, postgres,
+    source::{self, FileOptions},
+    spreadsheet,
+};
+
+// how often the file is checked for being rewritten
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+// a file still being written keeps changing, it is reloaded once it settles
+const SETTLE_INTERVAL: Duration = Duration::from_millis(200);
+
+// the file behind a table, and when it was last written
+pub(crate) struct Watched {
+    file: PathBuf,
+    stamp: Option<(SystemTime, u64)>,
+}
+
+// a single file on disk, pipes and followed files already keep up with what is written
+pub(crate) fn watch(path: &str, options: &FileOptions) -> Option<Watched> {
+    if pipe::is_pipe(path) || pipe::is_followed(path, options) || postgres::is_url(path) {
+        return None;
+    }
+    let file = database::split(path)
+        .or_else(|| spreadsheet::split(path))
+        .map_or(path, |(file, _)| file);
+    let file = PathBuf::from(source::root(file));
+    if !file.is_file() {
+        return None;
+    }
+    let stamp = stamp(&file);
+    Some(Watched { file, stamp })
+}
+
+fn stamp(file: &PathBuf) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(file).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// waits for the file to be rewritten, forever if nothing is watched
+pub(crate) async fn changed(watched: Option<Watched>) -> Watched {
+    let Some(mut watched) = watched else {
+        return std::future::pending().await;
+    };
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let mut stamp = stamp(&watched.file);
+        if stamp == watched.stamp {
+            continue;
+        }
+        loop {
+            tokio::time::sleep(SETTLE_INTERVAL).await;
+            let settled = self::stamp(&watched.file);
+            if settled == stamp {
+                break;
+            }
+            stamp = settled;
+        }
+        watched.stamp = stamp;
+        // a file that is gone for now has nothing to reload
+        if stamp.is_some() {
+            return watched;
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::{fs::File, path::Path};
+
+use datafusion::{
+    arrow::datatypes::Schema,
+    parquet::file::{
+        reader::{FileReader, SerializedFileReader},
+        statistics::Statistics,
+    },
+};
+
+use crate::{source, viewport::ColumnMap};
+
+#[derive(Debug, Clone)]
+pub(crate) struct ColumnInfo {
+    pub(crate) name: String,
+    pub(crate) data_type: String,
+    pub(crate) nullable: bool,
+    pub(crate) metadata: Vec<(String, String)>,
+    pub(crate) parquet: Option<ParquetColumnInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ParquetColumnInfo {
+    pub(crate) physical_type: String,
+    pub(crate) encodings: Vec<String>,
+    pub(crate) compression: String,
+    pub(crate) row_groups: Vec<String>,
+}
+
+impl ColumnInfo {
+    fn details(&self) -> String {
+        let nullable = if self.nullable {
+            "nullable"
+        } else {
+            "not null"
+        };
+        let mut text = format!("\n  {}, {}", self.data_type, nullable);
+        for (key, value) in self.metadata.iter() {
+            text.push_str(&format!("\n  {key}: {value}"));
+        }
+        if let Some(parquet) = &self.parquet {
+            text.push_str(&format!(
+                "\n  {} {} [{}]",
+                parquet.physical_type,
+                parquet.compression,
+                parquet.encodings.join(", ")
+            ));
+            for (i, stats) in parquet.row_groups.iter().enumerate() {
+                text.push_str(&format!("\n  rg{i}: {stats}"));
+            }
+        }
+        text
+    }
+}
+
+pub(crate) fn column_infos(schema: &Schema, path: &str) -> Vec<ColumnInfo> {
+    let mut columns: Vec<_> = schema
+        .fields()
+        .iter()
+        .map(|f| {
+            let mut metadata: Vec<_> = f
+                .metadata()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            metadata.sort();
+            ColumnInfo {
+                name: f.name().clone(),
+                data_type: f.data_type().to_string(),
+                nullable: f.is_nullable(),
+                metadata,
+                parquet: None,
+            }
+        })
+        .collect();
+    // datasets have a parquet file per partition, there is no single footer to read
+    if source::extension(path) == "parquet" && Path::new(path).is_file() {
+        if let Err(e) = add_parquet_info(&mut columns, path) {
+            log::warn!("could not read parquet metadata: {e}");
+        }
+    }
+    columns
+}
+
+fn add_parquet_info(
+    columns: &mut [ColumnInfo],
+    path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let metadata = reader.metadata();
+    for column in columns.iter_mut() {
+        let mut info: Option<ParquetColumnInfo> = None;
+        for row_group in metadata.row_groups() {
+            let Some(chunk) = row_group
+                .columns()
+                .iter()
+                .find(|c| c.column_path().string() == column.name)
+            else {
+                continue;
+            };
+            let info = info.get_or_insert_with(|| ParquetColumnInfo {
+                physical_type: chunk.column_type().to_string(),
+                encodings: chunk.encodings().iter().map(|e| e.to_string()).collect(),
+                compression: chunk.compression().to_string(),
+                row_groups: vec![],
+            });
+            let stats = match chunk.statistics() {
+                Some(stats) => format_statistics(stats),
+                None => "no statistics".to_string(),
+            };
+            info.row_groups
+                .push(format!("{} rows, {stats}", row_group.num_rows()));
+        }
+        column.parquet = info;
+    }
+    Ok(())
+}
+
+fn format_statistics(stats: &Statistics) -> String {
+    macro_rules! min_max {
+        ($($variant:ident),*) => {
+            match stats {
+                $(Statistics::$variant(s) => (s.min().to_string(), s.max().to_string()),)*
+            }
+        };
+    }
+    let nulls = format!("nulls: {}", stats.null_count());
+    if !stats.has_min_max_set() {
+        return nulls;
+    }
+    let (min, max) = min_max!(
+        Boolean,
+        Int32,
+        Int64,
+        Int96,
+        Float,
+        Double,
+        ByteArray,
+        FixedLenByteArray
+    );
+    format!("min: {min}, max: {max}, {nulls}")
+}
+
+// every column, hidden ones too so they can be found and shown again
+pub(crate) fn blocks(columns: &[ColumnInfo], column_map: &ColumnMap) -> Vec<(String, String)> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| match column_map.position(i) {
+            Some(_) => (c.name.clone(), c.details()),
+            None => (format!("{} (hidden)", c.name), c.details()),
+        })
+        .collect()
+}
+
+// what rewriting a file did to its columns, nothing when they still match
+pub(crate) fn changes(old: &Schema, new: &Schema) -> Vec<String> {
+    let mut changes = vec![];
+    for field in new.fields() {
+        match old.field_with_name(field.name()) {
+            Err(_) => changes.push(format!("added {} ({})", field.name(), field.data_type())),
+            Ok(was) if was.data_type() != field.data_type() => changes.push(format!(
+                "{} is now {}, was {}",
+                field.name(),
+                field.data_type(),
+                was.data_type()
+            )),
+            Ok(_) => {}
+        }
+    }
+    for field in old.fields() {
+        if new.field_with_name(field.name()).is_err() {
+            changes.push(format!("removed {}", field.name()));
+        }
+    }
+    let names = |schema: &Schema| -> Vec<String> {
+        schema.fields().iter().map(|f| f.name().clone()).collect()
+    };
+    if changes.is_empty() && names(old) != names(new) {
+        changes.push("the columns are in a different order".to_string());
+    }
+    changes
+}
+
+pub(crate) fn notice(title: &str, changes: &[String]) -> Vec<(String, String)> {
+    let body = changes.iter().map(|c| format!("\n  {c}")).collect();
+    vec![(format!("{title} changed on disk"), body)]
+}
@@ -0,0 +1,52 @@
+use std::{fs, path::PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::pipe;
+
+// a json file of settings kept per opened file, in the state directory
+fn store(name: &str) -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+        })?;
+    Some(dir.join("raam").join(name))
+}
+
+fn key(path: &str) -> String {
+    fs::canonicalize(path).map_or(path.to_string(), |p| p.to_string_lossy().into_owned())
+}
+
+fn read_store(name: &str) -> Map<String, Value> {
+    store(name)
+        .and_then(|file| fs::read_to_string(file).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+// the settings a store holds for a file. What comes through a pipe is different every time,
+// so it has none
+pub(crate) fn read(path: &str, name: &str) -> Option<Value> {
+    if pipe::is_pipe(path) {
+        return None;
+    }
+    read_store(name).remove(&key(path))
+}
+
+// nothing to keep removes the file from the store
+pub(crate) fn write(path: &str, name: &str, value: Option<Value>) {
+    let Some(file) = store(name).filter(|_| !pipe::is_pipe(path)) else {
+        return;
+    };
+    let mut all = read_store(name);
+    match value {
+        Some(value) => all.insert(key(path), value),
+        None => all.remove(&key(path)),
+    };
+    let written = fs::create_dir_all(file.parent().unwrap())
+        .and_then(|_| fs::write(&file, Value::from(all).to_string()));
+    if let Err(e) = written {
+        log::error!("could not save to {}: {e}", file.display());
+    }
+}
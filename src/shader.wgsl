@@ -5,6 +5,12 @@ struct CameraUniform {
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
 
+struct PaletteUniform {
+	colors: array<vec4<f32>, 3>,
+}
+@group(1) @binding(0)
+var<uniform> palette: PaletteUniform;
+
 struct VertexInput {
 	@location(0) pos: vec2<f32>,
 }
@@ -13,11 +19,13 @@ struct InstanceInput {
 	@location(1) offset: vec2<f32>,
 	@location(2) scale: vec2<f32>,
 	@location(3) alpha: f32,
+	@location(4) highlight: u32,
 }
 
 struct VertexOutput {
 	@builtin(position) clip_position: vec4<f32>,
 	@location(0) alpha: f32,
+	@location(1) color: vec3<f32>,
 };
 
 fn position_from_screen(screen_pos: vec2<f32>) -> vec4<f32> {
@@ -48,13 +56,13 @@ fn vs_main(
 	var out: VertexOutput;
 	out.clip_position = position_from_screen(model.pos * instance.scale + instance.offset);
 	out.alpha = srgb_to_linear(instance.alpha);
+	out.color = palette.colors[instance.highlight].rgb;
 	// out.alpha = instance.alpha;
 	return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-	return vec4f(0.39676, 0.46778, 0.82279, in.alpha);
-	// return vec4f(0.39676, 0.46778, 0.82279, 0.1);
+	return vec4f(in.color, in.alpha);
 }
 
@@ -0,0 +1,473 @@
+use std::{fs::File, path::Path, sync::Arc};
+
+use clap::Args;
+use datafusion::{
+    arrow::{
+        csv::{reader::Format, WriterBuilder},
+        datatypes::{DataType, Field, Schema},
+    },
+    common::plan_err,
+    datasource::{
+        file_format::{
+            file_compression_type::FileCompressionType, DEFAULT_SCHEMA_INFER_MAX_RECORD,
+        },
+        listing::ListingTableUrl,
+    },
+    error::{DataFusionError, Result},
+    execution::{context::SessionState, runtime_env::RuntimeEnv},
+    prelude::*,
+};
+use futures::StreamExt;
+
+use crate::{database, pipe, postgres, spreadsheet};
+
+#[derive(Debug, Clone, Args)]
+pub(crate) struct FileOptions {
+    /// CSV field delimiter, a tab for .tsv files and a comma otherwise
+    #[arg(long, value_parser = parse_byte)]
+    delimiter: Option<u8>,
+    /// CSV quote character
+    #[arg(long, default_value = "\"", value_parser = parse_byte)]
+    quote: u8,
+    /// CSV escape character
+    #[arg(long, value_parser = parse_byte)]
+    escape: Option<u8>,
+    /// CSV files have no header row
+    #[arg(long)]
+    no_header: bool,
+    /// Rows of CSV and JSON files read to infer the schema
+    #[arg(long, default_value_t = DEFAULT_SCHEMA_INFER_MAX_RECORD)]
+    infer_rows: usize,
+    // set from the window and terminal's `--follow`, a render can't wait for more rows
+    #[arg(skip)]
+    pub(crate) follow: bool,
+}
+
+impl FileOptions {
+    // the csv dialect, with the given delimiter unless one was chosen
+    pub(crate) fn csv_format(&self, delimiter: u8) -> Format {
+        let mut format = Format::default()
+            .with_header(!self.no_header)
+            .with_delimiter(self.delimiter.unwrap_or(delimiter))
+            .with_quote(self.quote);
+        if let Some(escape) = self.escape {
+            format = format.with_escape(escape);
+        }
+        format
+    }
+
+    // the same dialect for writing, so a saved file reads back with the same options
+    pub(crate) fn csv_writer(&self, delimiter: u8) -> WriterBuilder {
+        let mut builder = WriterBuilder::new()
+            .with_header(!self.no_header)
+            .with_delimiter(self.delimiter.unwrap_or(delimiter))
+            .with_quote(self.quote);
+        if let Some(escape) = self.escape {
+            builder = builder.with_escape(escape);
+        }
+        builder
+    }
+
+    pub(crate) fn infer_rows(&self) -> usize {
+        self.infer_rows
+    }
+
+    // the options as a view file keeps them, bytes as one character strings
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        let byte = |b: u8| (b as char).to_string();
+        serde_json::json!({
+            "delimiter": self.delimiter.map(byte),
+            "quote": byte(self.quote),
+            "escape": self.escape.map(byte),
+            "no_header": self.no_header,
+            "infer_rows": self.infer_rows,
+            "follow": self.follow,
+        })
+    }
+
+    // what a view file leaves out, or can't be read, is the default
+    pub(crate) fn from_json(value: &serde_json::Value) -> Self {
+        let byte = |key| {
+            value
+                .get(key)
+                .and_then(|v| v.as_str())
+                .and_then(|s| parse_byte(s).ok())
+        };
+        let flag = |key| value.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        Self {
+            delimiter: byte("delimiter"),
+            quote: byte("quote").unwrap_or(b'"'),
+            escape: byte("escape"),
+            no_header: flag("no_header"),
+            infer_rows: value
+                .get("infer_rows")
+                .and_then(|v| v.as_u64())
+                .map_or(DEFAULT_SCHEMA_INFER_MAX_RECORD, |n| n as usize),
+            follow: flag("follow"),
+        }
+    }
+}
+
+fn parse_byte(s: &str) -> Result<u8, String> {
+    match s {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        _ => Err(format!("expected a single ascii character, got {s}")),
+    }
+}
+
+// the query context shared by a window's tabs, which hands paging to database servers
+pub(crate) fn context() -> SessionContext {
+    let state =
+        SessionState::new_with_config_rt(SessionConfig::new(), Arc::new(RuntimeEnv::default()))
+            .add_physical_optimizer_rule(Arc::new(postgres::PushDown));
+    SessionContext::new_with_state(state)
+}
+
+// every table a path opens as a path of its own
+pub(crate) async fn expand(path: &str) -> Vec<String> {
+    if postgres::is_url(path) {
+        postgres::expand(path).await
+    } else {
+        expand_file(path)
+    }
+}
+
+// every table or sheet of a file as a path of its own
+pub(crate) fn expand_file(path: &str) -> Vec<String> {
+    if pipe::is_pipe(path) {
+        vec![path.to_string()]
+    } else if spreadsheet::split(path).is_some() {
+        spreadsheet::expand(path)
+    } else {
+        database::expand(path)
+    }
+}
+
+// where a table's files live, and what the files and directories look like
+struct Layout {
+    table_path: String,
+    sample: String,
+    format: String,
+    compression: FileCompressionType,
+    file_extension: String,
+    partitions: Vec<(String, DataType)>,
+}
+
+pub(crate) async fn register(
+    ctx: &SessionContext,
+    name: &str,
+    path: &str,
+    options: &FileOptions,
+    text_columns: &[String],
+) -> Result<()> {
+    // a pipe is read once, a cast starts from the type its first lines suggested
+    if pipe::is_pipe(path) || pipe::is_followed(path, options) {
+        return pipe::register(ctx, name, path, options);
+    }
+    // database columns already come typed, a cast starts from that type
+    if let Some((url, table)) = postgres::split(path) {
+        return postgres::register(ctx, name, url, table).await;
+    }
+    if let Some((file, table)) = database::split(path) {
+        return database::register(ctx, name, file, table);
+    }
+    if let Some((file, sheet)) = spreadsheet::split(path) {
+        return spreadsheet::register(ctx, name, file, sheet, text_columns);
+    }
+    let layout = if is_dataset(path) {
+        dataset_layout(ctx, path).await?
+    } else {
+        let file_name = Path::new(path)
+            .file_name()
+            .map(|f| f.to_string_lossy())
+            .unwrap_or_default();
+        file_layout(path.to_string(), path.to_string(), &file_name, vec![])
+    };
+    if text_columns.is_empty() || layout.format == "parquet" {
+        return register_layout(ctx, name, &layout, options, None).await;
+    }
+    // columns with a type override are read as the text in the file, and cast from there
+    register_layout(ctx, name, &layout, options, None).await?;
+    let inferred = ctx.table_provider(name).await?.schema();
+    let fields: Vec<_> = inferred
+        .fields()
+        .iter()
+        .filter(|f| !layout.partitions.iter().any(|(p, _)| p == f.name()))
+        .map(|f| {
+            if text_columns.contains(f.name()) {
+                Field::new(f.name(), DataType::Utf8, true)
+            } else {
+                f.as_ref().clone()
+            }
+        })
+        .collect();
+    register_layout(ctx, name, &layout, options, Some(&Schema::new(fields))).await
+}
+
+async fn register_layout(
+    ctx: &SessionContext,
+    name: &str,
+    layout: &Layout,
+    options: &FileOptions,
+    schema: Option<&Schema>,
+) -> Result<()> {
+    // registering again replaces the table, as when a column type changes
+    ctx.deregister_table(name)?;
+    let file_extension = &layout.file_extension;
+    let partitions = layout.partitions.clone();
+    match layout.format.as_str() {
+        "parquet" => {
+            let options = ParquetReadOptions {
+                file_extension,
+                table_partition_cols: partitions,
+                ..Default::default()
+            };
+            ctx.register_parquet(name, &layout.table_path, options)
+                .await
+        }
+        "json" | "ndjson" | "jsonl" => {
+            let options = NdJsonReadOptions {
+                schema,
+                schema_infer_max_records: options.infer_rows,
+                ..Default::default()
+            }
+            .file_extension(file_extension)
+            .file_compression_type(layout.compression)
+            .table_partition_cols(partitions);
+            ctx.register_json(name, &layout.table_path, options).await
+        }
+        format => {
+            let delimiter = options.delimiter.unwrap_or(match format {
+                "tsv" => b'\t',
+                _ => b',',
+            });
+            // datafusion infers the schema with the default quote and escape
+            let inferred = match schema {
+                None if options.quote != b'"' || options.escape.is_some() => {
+                    Some(infer_csv_schema(layout, options, delimiter)?)
+                }
+                _ => None,
+            };
+            let mut csv_options = CsvReadOptions::new()
+                .has_header(!options.no_header)
+                .delimiter(delimiter)
+                .quote(options.quote)
+                .schema_infer_max_records(options.infer_rows)
+                .file_extension(file_extension)
+                .file_compression_type(layout.compression)
+                .table_partition_cols(partitions);
+            if let Some(escape) = options.escape {
+                csv_options = csv_options.escape(escape);
+            }
+            if let Some(schema) = schema.or(inferred.as_ref()) {
+                csv_options = csv_options.schema(schema);
+            }
+            ctx.register_csv(name, &layout.table_path, csv_options)
+                .await
+        }
+    }
+}
+
+fn infer_csv_schema(layout: &Layout, options: &FileOptions, delimiter: u8) -> Result<Schema> {
+    let file = File::open(&layout.sample)?;
+    let reader = layout.compression.convert_read(file)?;
+    let format = options.csv_format(delimiter);
+    let (schema, _) = format.infer_schema(reader, Some(options.infer_rows))?;
+    Ok(schema)
+}
+
+// `data.csv.gz` is gzipped csv, listing tables select files on the whole `.csv.gz` suffix
+fn file_layout(
+    table_path: String,
+    sample: String,
+    file_name: &str,
+    partitions: Vec<(String, DataType)>,
+) -> Layout {
+    let (stem, compression) = match file_name.rsplit_once('.') {
+        Some((stem, ext)) => match ext.to_lowercase().as_str() {
+            "gz" | "gzip" => (stem, FileCompressionType::GZIP),
+            "bz2" => (stem, FileCompressionType::BZIP2),
+            "xz" => (stem, FileCompressionType::XZ),
+            "zst" | "zstd" => (stem, FileCompressionType::ZSTD),
+            _ => (file_name, FileCompressionType::UNCOMPRESSED),
+        },
+        None => (file_name, FileCompressionType::UNCOMPRESSED),
+    };
+    let suffixes = if compression.is_compressed() { 2 } else { 1 };
+    let file_extension = file_name
+        .rmatch_indices('.')
+        .nth(suffixes - 1)
+        .map(|(i, _)| file_name[i..].to_string())
+        .unwrap_or_default();
+    Layout {
+        table_path,
+        sample,
+        format: extension(stem),
+        compression,
+        file_extension,
+        partitions,
+    }
+}
+
+fn is_dataset(path: &str) -> bool {
+    Path::new(path).is_dir() || path.contains(['*', '?', '['])
+}
+
+// the format and hive style `key=value` partition columns are taken from the first data file
+async fn dataset_layout(ctx: &SessionContext, path: &str) -> Result<Layout> {
+    // partition directories are matched by the listing itself, a glob on them never matches
+    let table_path = path
+        .split('/')
+        .filter(|segment| !segment.ends_with("=*"))
+        .collect::<Vec<_>>()
+        .join("/");
+    let url = ListingTableUrl::parse(&table_path)?;
+    let store = ctx.runtime_env().object_store(&url)?;
+    let state = ctx.state();
+    let mut files = url.list_all_files(&state, store.as_ref(), "").await?;
+    while let Some(file) = files.next().await {
+        let location = file?.location;
+        let Some(file_name) = location.filename() else {
+            continue;
+        };
+        // skip markers like _SUCCESS and hidden files
+        if file_name.starts_with(['_', '.']) || !file_name.contains('.') {
+            continue;
+        }
+        let partitions = location
+            .parts()
+            .skip(url.prefix().parts().count())
+            .filter_map(|part| {
+                let (key, _) = part.as_ref().split_once('=')?;
+                Some((key.to_string(), DataType::Utf8))
+            })
+            .collect();
+        let sample = format!("/{location}");
+        return Ok(file_layout(table_path, sample, file_name, partitions));
+    }
+    plan_err!("no data files found in {path}")
+}
+
+pub(crate) fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+// the part of a path before any glob, which names the dataset
+pub(crate) fn root(path: &str) -> &str {
+    match path.find(['*', '?', '[']) {
+        Some(i) => path[..i]
+            .trim_end_matches(|c| c != '/')
+            .trim_end_matches('/'),
+        None => path.trim_end_matches('/'),
+    }
+}
+
+// a plain sql identifier from the file name, so other queries can refer to the table
+pub(crate) fn table_name(path: &str, taken: &[&str]) -> String {
+    // a table or sheet inside a database or workbook is named after itself, and tables
+    // outside postgres' public schema after the table alone
+    let inner = postgres::split(path)
+        .map(|(_, table)| table.map(|t| t.rsplit('.').next().unwrap_or(t)))
+        .or_else(|| database::split(path).map(|(_, table)| table))
+        .or_else(|| spreadsheet::split(path).map(|(_, sheet)| sheet))
+        .flatten();
+    let stem = match inner {
+        Some(table) => table.to_lowercase(),
+        None if path == "-" => "stdin".to_string(),
+        None => Path::new(root(path))
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase())
+            .unwrap_or_default(),
+    };
+    let mut base: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !base.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        base.insert_str(0, "t_");
+    }
+    let mut name = base.clone();
+    let mut n = 1;
+    while taken.contains(&name.as_str()) {
+        n += 1;
+        name = format!("{base}_{n}");
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn compression_is_told_by_the_last_suffix() {
+        let layout = |name: &str| {
+            let layout = file_layout(String::new(), String::new(), name, vec![]);
+            (layout.format, layout.compression, layout.file_extension)
+        };
+        let csv = |compression, suffix: &str| ("csv".to_string(), compression, suffix.to_string());
+        assert_eq!(
+            layout("data.csv"),
+            csv(FileCompressionType::UNCOMPRESSED, ".csv")
+        );
+        assert_eq!(
+            layout("data.csv.gz"),
+            csv(FileCompressionType::GZIP, ".csv.gz")
+        );
+        assert_eq!(
+            layout("DATA.CSV.ZST"),
+            csv(FileCompressionType::ZSTD, ".CSV.ZST")
+        );
+        assert_eq!(
+            layout("a.b.csv.bz2"),
+            csv(FileCompressionType::BZIP2, ".csv.bz2")
+        );
+        assert_eq!(layout("data.csv.xz").1, FileCompressionType::XZ);
+        // a compressed file without a format of its own
+        let (format, compression, _) = layout("data.gz");
+        assert_eq!(
+            (format.as_str(), compression),
+            ("", FileCompressionType::GZIP)
+        );
+    }
+
+    #[tokio::test]
+    async fn partition_globs_are_left_to_the_listing() {
+        let dir = std::env::temp_dir().join(format!("raam-dataset-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let partition = dir.join("year=2024");
+        fs::create_dir_all(&partition).unwrap();
+        fs::write(partition.join("_SUCCESS"), "").unwrap();
+        fs::write(partition.join("part-0.csv"), "a\n1\n").unwrap();
+
+        let dir_path = dir.to_string_lossy().to_string();
+        let layout = dataset_layout(&context(), &format!("{dir_path}/year=*/"))
+            .await
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(layout.table_path, format!("{dir_path}/"));
+        assert_eq!(layout.sample, format!("{dir_path}/year=2024/part-0.csv"));
+        assert_eq!(
+            (layout.format.as_str(), layout.file_extension.as_str()),
+            ("csv", ".csv")
+        );
+        assert_eq!(layout.partitions, [("year".to_string(), DataType::Utf8)]);
+    }
+
+    #[test]
+    fn delimiters_are_single_characters() {
+        assert_eq!(parse_byte(";"), Ok(b';'));
+        assert_eq!(parse_byte("\\t"), Ok(b'\t'));
+        assert_eq!(parse_byte("tab"), Ok(b'\t'));
+        assert!(parse_byte("").is_err());
+        assert!(parse_byte(";;").is_err());
+        assert!(parse_byte("é").is_err());
+    }
+}
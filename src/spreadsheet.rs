@@ -0,0 +1,322 @@
+use std::sync::Arc;
+
+use calamine::{open_workbook_auto, Data, Reader};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use datafusion::{
+    arrow::{
+        datatypes::{DataType, Field, Schema, TimeUnit},
+        record_batch::{RecordBatch, RecordBatchOptions},
+    },
+    common::plan_err,
+    datasource::MemTable,
+    error::{DataFusionError, Result},
+    prelude::*,
+};
+
+use crate::{
+    database::{external, Builder, Value},
+    source,
+};
+
+// rows searched for the header, below any title lines above the table
+const HEADER_SEARCH_ROWS: usize = 20;
+
+fn is_workbook(path: &str) -> bool {
+    matches!(
+        source::extension(path).as_str(),
+        "xlsx" | "xlsm" | "xlsb" | "xls" | "ods"
+    )
+}
+
+// `sales.xlsx#Q1` is one sheet of a workbook, the workbook on its own its first sheet
+pub(crate) fn split(path: &str) -> Option<(&str, Option<&str>)> {
+    if is_workbook(path) {
+        return Some((path, None));
+    }
+    // sheet names may hold a `#` themselves
+    path.match_indices('#')
+        .map(|(i, _)| (&path[..i], &path[i + 1..]))
+        .find(|(file, _)| is_workbook(file))
+        .map(|(file, sheet)| (file, Some(sheet)))
+}
+
+// every sheet of a workbook as a path of its own, any other path as it is
+pub(crate) fn expand(path: &str) -> Vec<String> {
+    let Some((file, None)) = split(path) else {
+        return vec![path.to_string()];
+    };
+    match open_workbook_auto(file) {
+        Ok(workbook) if !workbook.sheet_names().is_empty() => workbook
+            .sheet_names()
+            .iter()
+            .map(|sheet| format!("{file}#{sheet}"))
+            .collect(),
+        _ => vec![path.to_string()],
+    }
+}
+
+pub(crate) fn register(
+    ctx: &SessionContext,
+    name: &str,
+    file: &str,
+    sheet: Option<&str>,
+    text_columns: &[String],
+) -> Result<()> {
+    let mut workbook = open_workbook_auto(file).map_err(external)?;
+    let sheet = match sheet {
+        Some(sheet) => sheet.to_string(),
+        None => match workbook.sheet_names().first() {
+            Some(sheet) => sheet.clone(),
+            None => return plan_err!("{file} has no sheets"),
+        },
+    };
+    let range = workbook.worksheet_range(&sheet).map_err(external)?;
+    let rows: Vec<_> = range.rows().collect();
+    let Some((names, data)) = header(&rows) else {
+        return plan_err!("sheet {sheet} of {file} is empty");
+    };
+
+    let fields: Vec<_> = names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| {
+            // columns with a type override are read as the text in the sheet
+            let data_type = if text_columns.contains(&name) {
+                DataType::Utf8
+            } else {
+                column_type(data.iter().filter_map(|row| row.get(i)))
+            };
+            Field::new(name, data_type, true)
+        })
+        .collect();
+    let mut builders: Vec<_> = fields.iter().map(|f| Builder::new(f.data_type())).collect();
+    for row in data {
+        for (i, (builder, field)) in builders.iter_mut().zip(&fields).enumerate() {
+            builder.append(value(row.get(i).unwrap_or(&Data::Empty), field.data_type()))?;
+        }
+    }
+    let schema = Arc::new(Schema::new(fields));
+    let arrays = builders.iter_mut().map(Builder::finish).collect();
+    let options = RecordBatchOptions::new().with_row_count(Some(data.len()));
+    let batch = RecordBatch::try_new_with_options(schema.clone(), arrays, &options)?;
+    ctx.deregister_table(name)?;
+    ctx.register_table(
+        name,
+        Arc::new(MemTable::try_new(schema, vec![vec![batch]])?),
+    )?;
+    Ok(())
+}
+
+// the first filled row starts the table, unless it is a title line: a single cell above a
+// wider row. It is the header when it is all text, blanks included, otherwise the columns
+// get the names datafusion gives a csv file without one
+fn header<'a>(rows: &'a [&'a [Data]]) -> Option<(Vec<String>, &'a [&'a [Data]])> {
+    let filled = |row: &[Data]| row.iter().filter(|c| **c != Data::Empty).count();
+    let next =
+        |from: usize| (from..rows.len().min(HEADER_SEARCH_ROWS)).find(|&i| filled(rows[i]) > 0);
+    let mut top = rows.iter().position(|row| filled(row) > 0)?;
+    while let Some(below) = next(top + 1) {
+        if filled(rows[top]) > 1 || filled(rows[below]) <= 1 {
+            break;
+        }
+        top = below;
+    }
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or_default();
+    let is_header = rows[top]
+        .iter()
+        .all(|c| matches!(c, Data::String(_) | Data::Empty));
+    if !is_header {
+        let names = (1..=width).map(|i| format!("column_{i}")).collect();
+        return Some((names, &rows[top..]));
+    }
+
+    let mut names: Vec<String> = vec![];
+    for i in 0..width {
+        let base = match rows[top].get(i) {
+            Some(Data::String(s)) if !s.trim().is_empty() => s.trim().to_string(),
+            _ => format!("column_{}", i + 1),
+        };
+        let mut name = base.clone();
+        let mut n = 1;
+        while names.contains(&name) {
+            n += 1;
+            name = format!("{base}_{n}");
+        }
+        names.push(name);
+    }
+    Some((names, &rows[top + 1..]))
+}
+
+// a column keeps one type only if every filled cell has it, mixed columns are text
+fn column_type<'a>(cells: impl Iterator<Item = &'a Data>) -> DataType {
+    let mut column_type = None;
+    for cell in cells {
+        let cell_type = match cell {
+            Data::Empty | Data::Error(_) => continue,
+            Data::Int(_) => DataType::Int64,
+            Data::Float(v) if v.fract() == 0. && v.abs() < 2f64.powi(53) => DataType::Int64,
+            Data::Float(_) => DataType::Float64,
+            Data::Bool(_) => DataType::Boolean,
+            Data::DateTime(_) | Data::DateTimeIso(_) => match datetime(cell) {
+                Some(dt) if dt.time() == NaiveTime::MIN => DataType::Date32,
+                Some(_) => DataType::Timestamp(TimeUnit::Microsecond, None),
+                None => DataType::Utf8,
+            },
+            _ => DataType::Utf8,
+        };
+        column_type = Some(match (column_type, cell_type) {
+            (None, t) => t,
+            (Some(a), b) if a == b => a,
+            (Some(DataType::Int64 | DataType::Float64), DataType::Int64 | DataType::Float64) => {
+                DataType::Float64
+            }
+            (Some(DataType::Date32 | DataType::Timestamp(..)), t @ DataType::Timestamp(..))
+            | (Some(t @ DataType::Timestamp(..)), DataType::Date32) => t,
+            _ => return DataType::Utf8,
+        });
+    }
+    column_type.unwrap_or(DataType::Utf8)
+}
+
+// excel stores dates as numbers with a date format, ods as iso text
+fn datetime(cell: &Data) -> Option<NaiveDateTime> {
+    match cell {
+        Data::DateTime(dt) if dt.is_datetime() => dt.as_datetime(),
+        Data::DateTimeIso(s) => NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+            .ok()
+            .or_else(|| Some(NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?.into())),
+        _ => None,
+    }
+}
+
+// a cell of a text column as the sheet shows it, a date as a date rather than excel's number
+fn text(cell: &Data) -> String {
+    match cell {
+        Data::Float(v) if v.fract() == 0. && v.abs() < 2f64.powi(53) => (*v as i64).to_string(),
+        Data::DateTime(_) | Data::DateTimeIso(_) => match datetime(cell) {
+            Some(dt) if dt.time() == NaiveTime::MIN => dt.date().to_string(),
+            Some(dt) => dt.to_string(),
+            None => cell.to_string(),
+        },
+        _ => cell.to_string(),
+    }
+}
+
+fn value(cell: &Data, data_type: &DataType) -> Value {
+    match (cell, data_type) {
+        (Data::Empty | Data::Error(_), _) => Value::Null,
+        (_, DataType::Utf8) => Value::Text(text(cell)),
+        (Data::Int(v), _) => Value::Int(*v),
+        (Data::Float(v), _) => Value::Real(*v),
+        (Data::Bool(v), _) => Value::Bool(*v),
+        (_, DataType::Date32) => match datetime(cell) {
+            Some(dt) => Value::Int(dt.and_utc().timestamp().div_euclid(86_400)),
+            None => Value::Null,
+        },
+        (_, DataType::Timestamp(..)) => match datetime(cell) {
+            Some(dt) => Value::Int(dt.and_utc().timestamp_micros()),
+            None => Value::Null,
+        },
+        _ => Value::Text(text(cell)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use calamine::{ExcelDateTime, ExcelDateTimeType};
+
+    use super::*;
+
+    fn text(s: &str) -> Data {
+        Data::String(s.to_string())
+    }
+
+    // excel's number for a day, and a time on it
+    fn date(serial: f64) -> Data {
+        Data::DateTime(ExcelDateTime::new(
+            serial,
+            ExcelDateTimeType::DateTime,
+            false,
+        ))
+    }
+
+    fn names(rows: &[Vec<Data>]) -> (Vec<String>, usize) {
+        let rows: Vec<_> = rows.iter().map(Vec::as_slice).collect();
+        let (names, data) = header(&rows).unwrap();
+        (names, data.len())
+    }
+
+    #[test]
+    fn the_first_row_is_the_header_even_with_blanks() {
+        let rows = [
+            vec![text("id"), Data::Empty, text("id")],
+            vec![Data::Int(1), text("a"), Data::Int(2)],
+            vec![Data::Int(2), text("b"), Data::Int(3)],
+        ];
+        assert_eq!(
+            names(&rows),
+            (vec!["id".into(), "column_2".into(), "id_2".into()], 2)
+        );
+    }
+
+    #[test]
+    fn title_lines_above_the_table_are_skipped() {
+        let rows = [
+            vec![text("Sales 2024")],
+            vec![],
+            vec![text("region"), text("amount")],
+            vec![text("north"), Data::Int(3)],
+        ];
+        assert_eq!(names(&rows), (vec!["region".into(), "amount".into()], 1));
+        // a single column keeps its header
+        let rows = [vec![text("name")], vec![text("a")], vec![text("b")]];
+        assert_eq!(names(&rows), (vec!["name".into()], 2));
+    }
+
+    #[test]
+    fn a_first_row_of_values_is_data() {
+        let rows = [
+            vec![text("a"), Data::Float(1.5)],
+            vec![text("b"), Data::Float(2.5)],
+        ];
+        assert_eq!(
+            names(&rows),
+            (vec!["column_1".into(), "column_2".into()], 2)
+        );
+    }
+
+    #[test]
+    fn columns_take_the_type_every_cell_has() {
+        let column = |cells: &[Data]| column_type(cells.iter());
+        assert_eq!(
+            column(&[Data::Int(1), Data::Float(2.), Data::Empty]),
+            DataType::Int64
+        );
+        assert_eq!(column(&[Data::Int(1), Data::Float(2.5)]), DataType::Float64);
+        assert_eq!(
+            column(&[Data::Bool(true), Data::Error(calamine::CellErrorType::NA)]),
+            DataType::Boolean
+        );
+        assert_eq!(column(&[Data::Int(1), text("x")]), DataType::Utf8);
+        assert_eq!(column(&[date(45292.)]), DataType::Date32);
+        assert_eq!(
+            column(&[date(45292.), date(45292.5)]),
+            DataType::Timestamp(TimeUnit::Microsecond, None)
+        );
+        assert_eq!(column(&[Data::Empty]), DataType::Utf8);
+    }
+
+    #[test]
+    fn text_columns_show_values_as_the_sheet_does() {
+        let shown = |cell: &Data| match value(cell, &DataType::Utf8) {
+            Value::Text(text) => text,
+            _ => panic!("{cell:?} is not text"),
+        };
+        assert_eq!(shown(&Data::Float(3.)), "3");
+        assert_eq!(shown(&Data::Float(2.5)), "2.5");
+        assert_eq!(shown(&date(45292.)), "2024-01-01");
+        assert_eq!(shown(&date(45292.5)), "2024-01-01 12:00:00");
+        assert_eq!(shown(&Data::DateTimeIso("2024-01-01".into())), "2024-01-01");
+        assert!(matches!(value(&Data::Empty, &DataType::Utf8), Value::Null));
+    }
+}
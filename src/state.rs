@@ -1,36 +1,79 @@
-use std::time::Instant;
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use datafusion::{
     arrow::{
+        datatypes::DataType,
         error::ArrowError,
         record_batch::RecordBatch,
         util::display::{ArrayFormatter, FormatOptions},
     },
-    prelude::*,
+    prelude::SessionContext,
 };
 use glyphon::{
     Attrs, FontSystem, Metrics, Resolution, SwashCache, TextArea, TextAtlas, TextBounds,
     TextRenderer,
 };
-use tokio::{runtime::Builder, sync::oneshot};
 use wgpu::{MultisampleState, TextureFormat};
 use winit::{
-    event::{MouseScrollDelta, WindowEvent},
+    dpi::PhysicalPosition,
+    event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    event_loop::EventLoopProxy,
+    keyboard::{Key, ModifiersState, NamedKey},
     window::Window,
 };
 
+use crate::{
+    camera::Camera,
+    cast::CAST_TYPES,
+    config::{self, Action, Config},
+    detail,
+    edit::{Editor, Target},
+    histogram::HIST_HEIGHT,
+    line::{self, Highlight, LinePipeline},
+    nested,
+    panel::{Panel, PanelKind},
+    profile, schema,
+    session::{self, Place, SavedTab, Session, Setup},
+    source::{self, FileOptions},
+    tab::{Opening, Tab, TAB_WIDTH},
+    target::RenderTarget,
+    theme::Theme,
+    viewport::{ColumnMap, Hit, Viewport},
+    worker::{Notify, Request, Response},
+};
+
 pub(crate) struct State<'a> {
-    pub(crate) surface: wgpu::Surface<'a>,
+    pub(crate) target: RenderTarget<'a>,
     pub(crate) device: wgpu::Device,
     pub(crate) queue: wgpu::Queue,
-    pub(crate) config: wgpu::SurfaceConfiguration,
     pub(crate) size: winit::dpi::PhysicalSize<u32>,
-    // ctx: SessionContext,
+    ctx: SessionContext,
+    options: FileOptions,
+    notify: Notify,
     text_system: TextSystem,
-    offsets: Offsets,
-    query_tx: tokio::sync::mpsc::Sender<usize>,
-    results_rx: tokio::sync::mpsc::Receiver<Vec<RecordBatch>>,
-    last_update: usize,
+    line_pipeline: LinePipeline,
+    tabs: Vec<Tab>,
+    // dropped files whose workers are still opening them
+    opening: Vec<Opening>,
+    active: usize,
+    panel: Option<Panel>,
+    cursor: PhysicalPosition<f64>,
+    last_click: Option<(Instant, (usize, usize))>,
+    clipboard: Option<arboard::Clipboard>,
+    modifiers: ModifiersState,
+    editor: Option<Editor>,
+    // a header being dragged to another position
+    dragging: Option<usize>,
+    // the view file opened or last saved, which saving writes to again
+    session: Option<PathBuf>,
+    // each tab's setup as it arrives from its worker, the view is saved once all are in
+    saving: Option<Vec<Option<Setup>>>,
+    // swapped for a new one once the config file is edited
+    config: Arc<Config>,
 }
 
 struct Cell {
@@ -46,46 +89,54 @@ impl Cell {
 }
 
 impl<'a> State<'a> {
-    pub(crate) async fn new(window: &'a Window, instance: wgpu::Instance) -> Self {
-        let (query_tx, mut query_rx) = tokio::sync::mpsc::channel::<usize>(2);
-        let (results_tx, results_rx) = tokio::sync::mpsc::channel::<Vec<RecordBatch>>(2);
-        let (field_tx, field_rx) = oneshot::channel();
-
-        let rt = Builder::new_current_thread().enable_all().build().unwrap();
-        std::thread::spawn(move || {
-            rt.block_on(async move {
-                let ctx = SessionContext::new();
-                ctx.register_csv("example", "measurements.csv", CsvReadOptions::new())
-                    .await
-                    .unwrap();
-                let df = ctx
-                    .sql("SELECT inlet_temperature, outlet_temperature, energy_usage FROM example")
-                    .await
-                    .unwrap();
-                let field_names = df
-                    .schema()
-                    .fields()
-                    .iter()
-                    .map(|f| f.name().clone())
-                    .collect();
-                field_tx.send(field_names).unwrap();
-
-                while let Some(skip) = query_rx.recv().await {
-                    let now = Instant::now();
-                    let batches = df
-                        .clone()
-                        .limit(skip, Some(100))
-                        .unwrap()
-                        .collect()
-                        .await
-                        .unwrap();
-                    results_tx.send(batches).await.unwrap();
-                    println!("query done in: {:?}", now.elapsed());
-                }
-            })
+    pub(crate) async fn new(
+        window: &'a Window,
+        instance: wgpu::Instance,
+        path: String,
+        options: FileOptions,
+        session: Option<(PathBuf, Session)>,
+        proxy: EventLoopProxy<()>,
+    ) -> Result<Self, String> {
+        let ctx = source::context();
+        let notify = Notify::new(move || {
+            proxy.send_event(()).ok();
         });
+        config::watch(notify.clone());
+        let viewport = grid_viewport(&config::get(), 0., 0.);
+        let (opened, tabs, active) = match session {
+            // a view file opens the tabs it saved, as they were
+            Some((file, session)) => {
+                let tabs: Vec<_> = session
+                    .tabs
+                    .into_iter()
+                    .map(|t| (t.path, t.options, Some((t.setup, t.place))))
+                    .collect();
+                (Some(file), tabs, session.active)
+            }
+            // a database opens a tab for each of its tables
+            None => {
+                let paths = source::expand(&path).await.into_iter();
+                let tabs = paths.map(|path| (path, options.clone(), None)).collect();
+                (None, tabs, 0)
+            }
+        };
+        let mut tabs = tabs.into_iter();
+        let (path, tab_options, saved) = tabs.next().unwrap();
+        let (setup, place) = saved.unzip();
+        let mut tab = Tab::open(
+            &ctx,
+            path,
+            tab_options,
+            setup,
+            &[],
+            notify.clone(),
+            viewport,
+        )
+        .await?;
+        if let Some(place) = place {
+            tab.go_to(&place);
+        }
         println!("outside thread");
-        let field_names = field_rx.await.unwrap();
 
         let size = window.inner_size();
 
@@ -100,17 +151,7 @@ impl<'a> State<'a> {
             .await
             .unwrap();
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
-                    label: None,
-                },
-                None,
-            )
-            .await
-            .unwrap();
+        let (device, queue) = request_device(&adapter).await;
 
         let surface_caps = surface.get_capabilities(&adapter);
 
@@ -134,41 +175,147 @@ impl<'a> State<'a> {
 
         surface.configure(&device, &config);
 
-        let text_system = TextSystem::new(&device, &queue, surface_format, field_names);
-        query_tx.send(0).await.unwrap();
-        let last_update = 0;
+        let target = RenderTarget::Surface { surface, config };
+        let mut state = Self::with_target(device, queue, target, size, ctx, options, notify, tab);
+        state.session = opened;
+        for (path, options, saved) in tabs {
+            let taken: Vec<_> = state.tabs.iter().map(|t| t.table.as_str()).collect();
+            let (width, height) = (state.size.width as f32, state.size.height as f32);
+            let viewport = grid_viewport(&state.config, width, height);
+            let (setup, place) = saved.unzip();
+            let opened = Tab::open(
+                &state.ctx,
+                path,
+                options,
+                setup,
+                &taken,
+                state.notify.clone(),
+                viewport,
+            )
+            .await;
+            state.add_tab(opened, place);
+        }
+        state.switch_tab(active);
+        Ok(state)
+    }
+
+    pub(crate) async fn headless(
+        instance: wgpu::Instance,
+        path: String,
+        file_options: FileOptions,
+        nrows: usize,
+        width: Option<u32>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let ctx = source::context();
+        let config = config::get();
+        let viewport = grid_viewport(&config, 0., 0.);
+        let tab = Tab::open(
+            &ctx,
+            path,
+            file_options.clone(),
+            None,
+            &[],
+            Notify::default(),
+            viewport,
+        )
+        .await?;
 
-        let offsets = Offsets::default();
+        let mut options = wgpu::RequestAdapterOptions::default();
+        let adapter = match instance.request_adapter(&options).await {
+            Some(adapter) => adapter,
+            None => {
+                // software rasterizers such as lavapipe only show up as fallback adapters
+                options.force_fallback_adapter = true;
+                instance
+                    .request_adapter(&options)
+                    .await
+                    .ok_or("no suitable graphics adapter found")?
+            }
+        };
+        log::info!("rendering with {:?}", adapter.get_info());
+        let (device, queue) = request_device(&adapter).await;
 
-        Self {
-            surface,
+        let width = width.unwrap_or(tab.columns.len().max(1) as u32 * config.column_width as u32);
+        let tab_bar = if tab.progress.is_some() {
+            config.row_height
+        } else {
+            0.
+        };
+        let height = (tab_bar + config.row_height + HIST_HEIGHT) as u32
+            + nrows as u32 * config.row_height as u32;
+        let size = winit::dpi::PhysicalSize::new(width, height);
+        let target = RenderTarget::texture(&device, width, height);
+        Ok(Self::with_target(
             device,
             queue,
-            config,
+            target,
             size,
+            ctx,
+            file_options,
+            Notify::default(),
+            tab,
+        ))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn with_target(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        target: RenderTarget<'a>,
+        size: winit::dpi::PhysicalSize<u32>,
+        ctx: SessionContext,
+        options: FileOptions,
+        notify: Notify,
+        mut tab: Tab,
+    ) -> Self {
+        let config = config::get();
+        let text_system =
+            TextSystem::new(&device, &queue, target.format(), &config, tab.field_names());
+        let line_pipeline = LinePipeline::new(&device, target.format(), &config.theme.highlights);
+        tab.viewport.resize(size.width as f32, size.height as f32);
+
+        let mut state = Self {
+            target,
+            device,
+            queue,
+            size,
+            ctx,
+            options,
+            notify,
             text_system,
-            offsets,
-            query_tx,
-            results_rx,
-            last_update,
-        }
+            line_pipeline,
+            tabs: vec![tab],
+            opening: Vec::new(),
+            active: 0,
+            panel: None,
+            cursor: PhysicalPosition::default(),
+            last_click: None,
+            clipboard: None,
+            modifiers: ModifiersState::default(),
+            editor: None,
+            dragging: None,
+            session: None,
+            saving: None,
+            config,
+        };
+        state.update_tab_bar();
+        state
     }
 
     pub(crate) fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            for tab in self.tabs.iter_mut() {
+                tab.viewport
+                    .resize(new_size.width as f32, new_size.height as f32);
+            }
+            self.target
+                .resize(&self.device, new_size.width, new_size.height);
         }
     }
 
     pub(crate) fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let frame = self.surface.get_current_texture()?;
-
-        let view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let frame = self.target.frame()?;
 
         let mut encoder = self
             .device
@@ -177,16 +324,11 @@ impl<'a> State<'a> {
             });
 
         {
-            let bg_color = wgpu::Color {
-                r: 0.,
-                g: 0.005,
-                b: 0.06,
-                a: 1.0,
-            };
+            let bg_color = self.config.theme.clear_color();
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: &frame.view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(bg_color),
@@ -197,6 +339,11 @@ impl<'a> State<'a> {
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
+            let (width, height) = (self.target.width(), self.target.height());
+            let grid_right = self.grid_right().clamp(0., width as f32);
+            render_pass.set_scissor_rect(0, 0, grid_right as u32, height);
+            self.line_pipeline.render(&mut render_pass);
+            render_pass.set_scissor_rect(0, 0, width, height);
             self.text_system.render(&mut render_pass);
         }
         self.queue.submit(std::iter::once(encoder.finish()));
@@ -205,54 +352,917 @@ impl<'a> State<'a> {
         Ok(())
     }
 
+    pub(crate) fn scroll_to_row(&mut self, row: usize) {
+        let tab = &mut self.tabs[self.active];
+        tab.viewport.scroll_to_row(row);
+        let start = tab.data.request_from(row);
+        tab.request_rows(start);
+    }
+
+    // blocks until the current page and every column's distribution have arrived, after a
+    // pipe has been read to the end
+    pub(crate) fn wait_for_data(&mut self) -> Result<(), String> {
+        let active = self.active;
+        let ncols = self.tabs[active].columns.len();
+        let mut pending = ncols;
+        let mut page_loaded = false;
+        let mut reading = self.tabs[active].progress.is_some_and(|p| !p.done);
+        let page_size = self.config.page_size;
+        while pending > 0 || !page_loaded || reading {
+            let tab = &mut self.tabs[active];
+            if let Some(start) = tab.data.request(&tab.viewport, page_size) {
+                tab.request_rows(start);
+            }
+            let Some(response) = tab.results_rx.blocking_recv() else {
+                break;
+            };
+            match &response {
+                Response::Rows(skip, _) if *skip == tab.data.requested() => page_loaded = true,
+                Response::Distribution(..) => pending = pending.saturating_sub(1),
+                // nothing but a page that couldn't be read is told about while rendering
+                Response::Message(message) => return Err(message.clone()),
+                // the distributions are computed again over every row
+                Response::Progress(progress) if progress.done => {
+                    reading = false;
+                    pending = ncols;
+                }
+                _ => {}
+            }
+            self.handle_response(active, response);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn read_rgba(&self) -> Option<Vec<u8>> {
+        self.target.read_rgba(&self.device, &self.queue)
+    }
+
     pub(crate) fn process_input(&mut self, event: &WindowEvent) -> bool {
         match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                false
+            }
+            // typing into a cell takes every key until it is kept or cancelled
+            WindowEvent::KeyboardInput {
+                event:
+                    event @ KeyEvent {
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if self.is_editing() => self.type_into_editor(event),
             WindowEvent::MouseWheel {
                 delta: MouseScrollDelta::LineDelta(x, y),
                 ..
             } => {
-                self.offsets.update(*x, *y, 10.);
+                if let Some(panel) = self
+                    .panel
+                    .as_mut()
+                    .filter(|_| Panel::contains(self.cursor.x as f32, self.size.width as f32))
+                {
+                    panel.scroll(*y, self.config.scroll_speed);
+                } else {
+                    let speed = self.config.scroll_speed;
+                    self.tabs[self.active].viewport.scroll(*x, *y, speed);
+                }
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = *position;
+                false
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let (x, y) = (self.cursor.x as f32, self.cursor.y as f32);
+                match &self.panel {
+                    Some(panel) if Panel::contains(x, self.size.width as f32) => {
+                        match (panel.kind, panel.entry_at(y)) {
+                            // a hidden column is shown again
+                            (PanelKind::Schema, Some(col)) => {
+                                let tab = &mut self.tabs[self.active];
+                                tab.column_map.show(col);
+                                tab.arrange();
+                                match tab.column_map.position(col) {
+                                    Some(position) => tab.viewport.scroll_to_column(position),
+                                    None => return false,
+                                }
+                                true
+                            }
+                            (PanelKind::Profile(col), Some(i))
+                                if (1..=CAST_TYPES.len() + 1).contains(&i) =>
+                            {
+                                let data_type = CAST_TYPES.get(i - 1).map(|(_, t)| t.clone());
+                                self.cast_column(col, data_type)
+                            }
+                            (PanelKind::Detail { row, col }, Some(1)) => {
+                                self.copy_value(row, col);
+                                false
+                            }
+                            _ => false,
+                        }
+                    }
+                    _ if x >= self.grid_right() => false,
+                    _ if y < self.tab_bar_height() => self.switch_tab((x / TAB_WIDTH) as usize),
+                    _ => match self.tabs[self.active].hit(x, y) {
+                        // a header opens its profile once it is let go where it was grabbed
+                        Some(Hit::Header(col)) => {
+                            self.dragging = Some(col);
+                            false
+                        }
+                        Some(Hit::Cell { row, col }) => {
+                            let cell = (row, col);
+                            let double_click = self.last_click.is_some_and(|(time, last)| {
+                                last == cell && time.elapsed() < Duration::from_millis(400)
+                            });
+                            self.last_click = Some((Instant::now(), cell));
+                            self.tabs[self.active].selected = Some(cell);
+                            if double_click {
+                                self.open_detail(row, col);
+                            } else {
+                                self.open_tree(row, col);
+                            }
+                            true
+                        }
+                        None => false,
+                    },
+                }
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let Some(col) = self.dragging.take() else {
+                    return false;
+                };
+                let (x, y) = (self.cursor.x as f32, self.cursor.y as f32);
+                let tab = &mut self.tabs[self.active];
+                let position = match tab.viewport.hit(x, y) {
+                    Some(Hit::Header(position) | Hit::Cell { col: position, .. }) => position,
+                    None => return false,
+                };
+                if tab.column_map.position(col) == Some(position) {
+                    return self.open_profile(col);
+                }
+                tab.column_map.move_to(col, position);
+                tab.arrange();
                 true
             }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: Key::Character(c),
+                        ..
+                    },
+                ..
+            } => match self.config.action(c, self.modifiers.control_key()) {
+                Some(action) => self.act(action),
+                None => false,
+            },
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: Key::Named(NamedKey::Backspace),
+                        ..
+                    },
+                ..
+            } => {
+                let tab = &self.tabs[self.active];
+                tab.viewing.is_some() && tab.query_tx.try_send(Request::Back).is_ok()
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: Key::Named(NamedKey::F2),
+                        ..
+                    },
+                ..
+            } => {
+                self.panel = match self.panel {
+                    Some(Panel {
+                        kind: PanelKind::Schema,
+                        ..
+                    }) => None,
+                    _ => Some(Panel::new(
+                        &mut self.text_system.font_system,
+                        self.text_system.metrics,
+                        PanelKind::Schema,
+                        &schema::blocks(
+                            &self.tabs[self.active].columns,
+                            &self.tabs[self.active].column_map,
+                        ),
+                    )),
+                };
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: Key::Named(NamedKey::Escape),
+                        ..
+                    },
+                ..
+            } => self.panel.take().is_some(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: Key::Named(NamedKey::Enter),
+                        ..
+                    },
+                ..
+            } => match self.tabs[self.active].selected {
+                Some((row, col)) => self.open_detail(row, col),
+                None => false,
+            },
+            // the tabs show up once their workers have opened the files
+            WindowEvent::DroppedFile(path) => {
+                for path in source::expand_file(&path.to_string_lossy()) {
+                    self.open_tab(path, self.options.clone());
+                }
+                false
+            }
+
+            _ => false,
+        }
+    }
+
+    fn open_profile(&mut self, col: usize) -> bool {
+        let tab = &self.tabs[self.active];
+        if tab.query_tx.try_send(Request::Profile(col)).is_err() {
+            return false;
+        }
+        let name = tab.columns[col].name.clone();
+        let blocks = self.profile_blocks(col, vec![(name, "\n  computing...".to_string())]);
+        self.panel = Some(Panel::new(
+            &mut self.text_system.font_system,
+            self.text_system.metrics,
+            PanelKind::Profile(col),
+            &blocks,
+        ));
+        true
+    }
+
+    // the column's type and the types it can be cast to come before the statistics
+    fn profile_blocks(&self, col: usize, profile: Vec<(String, String)>) -> Vec<(String, String)> {
+        let tab = &self.tabs[self.active];
+        let mut blocks = vec![(
+            "type".to_string(),
+            format!("\n  {}\n\n  cast to", tab.columns[col].data_type),
+        )];
+        blocks.extend(
+            CAST_TYPES
+                .iter()
+                .map(|(name, _)| (format!("  [ {name} ]"), String::new())),
+        );
+        blocks.push(("  [ reset ]".to_string(), String::new()));
+        if let Some((_, failed)) = tab.cast_report.filter(|(c, _)| *c == col) {
+            blocks.push((
+                String::new(),
+                format!("\n  {failed} values could not be parsed and are null now"),
+            ));
+        }
+        blocks.extend(profile);
+        blocks
+    }
+
+    // the column of the open profile, or else of the selected cell
+    fn current_column(&self) -> Option<usize> {
+        match self.panel.as_ref().map(|p| p.kind) {
+            Some(PanelKind::Profile(col)) => Some(col),
+            _ => self.tabs[self.active].selected.map(|(_, col)| col),
+        }
+    }
 
+    // what a key is bound to, as the config has it
+    fn act(&mut self, action: Action) -> bool {
+        let tab = &self.tabs[self.active];
+        let request = match action {
+            Action::Undo => Request::Undo,
+            Action::Redo => Request::Redo,
+            Action::SaveEdits => Request::Save,
+            Action::ExportSql => Request::ExportSql,
+            Action::Drill => match (&tab.viewing, tab.selected) {
+                (Some(_), Some((row, _))) => Request::Drill(row),
+                _ => return false,
+            },
+            Action::Promote => match self.panel.as_ref().map(|p| p.kind) {
+                Some(PanelKind::Tree { col, .. }) => Request::Promote(col),
+                _ => return false,
+            },
+            Action::Edit => return self.start_edit(),
+            Action::AddColumn | Action::Group => {
+                self.editor = Some(match action {
+                    Action::Group => Editor::group(),
+                    _ => Editor::column(),
+                });
+                self.show_editor();
+                return true;
+            }
+            Action::Hide | Action::ShowAll | Action::Pin | Action::MoveLeft | Action::MoveRight => {
+                return self.arrange_column(action)
+            }
+            Action::SaveView => return self.start_saving(),
+        };
+        tab.query_tx.try_send(request).is_ok()
+    }
+
+    fn arrange_column(&mut self, action: Action) -> bool {
+        let col = self.current_column();
+        let tab = &mut self.tabs[self.active];
+        let map = &mut tab.column_map;
+        let position = col.and_then(|col| map.position(col));
+        let arranged = match (action, col, position) {
+            (Action::ShowAll, ..) => map.show_all(),
+            (Action::Hide, Some(col), _) => map.hide(col),
+            (Action::Pin, Some(col), _) => map.toggle_pin(col),
+            (Action::MoveLeft, Some(col), Some(position)) if position > 0 => {
+                map.move_to(col, position - 1)
+            }
+            (Action::MoveRight, Some(col), Some(position)) => map.move_to(col, position + 1),
             _ => false,
+        };
+        if !arranged {
+            return false;
+        }
+        tab.arrange();
+        if let Some(col) = col.filter(|&col| tab.column_map.position(col).is_none()) {
+            tab.selected = None;
+            if self.current_column() == Some(col) {
+                self.panel = None;
+            }
         }
+        true
+    }
+
+    fn cast_column(&mut self, col: usize, data_type: Option<DataType>) -> bool {
+        let tab = &mut self.tabs[self.active];
+        tab.cast_report = None;
+        tab.query_tx.try_send(Request::Cast(col, data_type)).is_ok()
+    }
+
+    fn open_tree(&mut self, row: usize, col: usize) -> bool {
+        let tab = &self.tabs[self.active];
+        let Some((array, index)) = tab.data.value_at(row, col) else {
+            return false;
+        };
+        if !array.data_type().is_nested() {
+            return false;
+        }
+        let mut body = nested::tree(array, index);
+        if matches!(array.data_type(), DataType::Struct(_)) {
+            if let Some(key) = self.config.key(Action::Promote) {
+                body.push_str(&format!("\n\npress {key} to promote the fields to columns"));
+            }
+        }
+        let title = format!("{}, row {}", tab.columns[col].name, row + 1);
+        self.panel = Some(Panel::new(
+            &mut self.text_system.font_system,
+            self.text_system.metrics,
+            PanelKind::Tree { row, col },
+            &[(title, body)],
+        ));
+        true
+    }
+
+    fn open_detail(&mut self, row: usize, col: usize) -> bool {
+        let tab = &self.tabs[self.active];
+        let Some((array, index)) = tab.data.value_at(row, col) else {
+            return false;
+        };
+        if array.data_type().is_nested() {
+            return self.open_tree(row, col);
+        }
+        let text = detail::detail_text(array, index);
+        let title = format!("{}, row {}", tab.columns[col].name, row + 1);
+        self.panel = Some(Panel::new(
+            &mut self.text_system.font_system,
+            self.text_system.metrics,
+            PanelKind::Detail { row, col },
+            &[
+                (title, String::new()),
+                ("[ copy ]".to_string(), String::new()),
+                (String::new(), text),
+            ],
+        ));
+        true
+    }
+
+    fn copy_value(&mut self, row: usize, col: usize) {
+        let Some(text) = self.tabs[self.active]
+            .data
+            .value_at(row, col)
+            .map(|(array, index)| detail::detail_text(array, index))
+        else {
+            return;
+        };
+        // the clipboard is served from this process on some platforms, so keep it alive
+        if self.clipboard.is_none() {
+            self.clipboard = arboard::Clipboard::new()
+                .map_err(|e| log::warn!("clipboard unavailable: {e}"))
+                .ok();
+        }
+        if let Some(clipboard) = self.clipboard.as_mut() {
+            if let Err(e) = clipboard.set_text(text) {
+                log::warn!("copy failed: {e}");
+            }
+        }
+    }
+
+    fn grid_right(&self) -> f32 {
+        let width = self.target.width() as f32;
+        self.panel.as_ref().map_or(width, |_| Panel::left(width))
+    }
+
+    // the tab bar only shows up once there is more than one file open, or a pipe's row count
+    // or a grouping to show
+    fn tab_bar_height(&self) -> f32 {
+        if self.tabs.len() > 1
+            || self
+                .tabs
+                .iter()
+                .any(|t| t.progress.is_some() || t.viewing.is_some())
+        {
+            self.config.row_height
+        } else {
+            0.
+        }
+    }
+
+    fn open_tab(&mut self, path: String, options: FileOptions) {
+        let taken: Vec<_> = (self.tabs.iter().map(|t| t.table.as_str()))
+            .chain(self.opening.iter().map(|o| o.table.as_str()))
+            .collect();
+        let opening = Opening::new(&self.ctx, path, options, None, &taken, self.notify.clone());
+        self.opening.push(opening);
+    }
+
+    // the tabs whose workers have opened their files since the last frame
+    fn add_opened_tabs(&mut self) {
+        let mut i = 0;
+        while i < self.opening.len() {
+            let Some(opened) = self.opening[i].opened() else {
+                i += 1;
+                continue;
+            };
+            let opening = self.opening.remove(i);
+            let (width, height) = (self.size.width as f32, self.size.height as f32);
+            let viewport = grid_viewport(&self.config, width, height);
+            self.add_tab(Tab::new(opening, opened, viewport), None);
+        }
+    }
+
+    fn add_tab(&mut self, opened: Result<Tab, String>, place: Option<Place>) -> bool {
+        let mut tab = match opened {
+            Ok(tab) => tab,
+            Err(e) => {
+                self.show_notice(e);
+                return true;
+            }
+        };
+        if let Some(place) = place {
+            tab.go_to(&place);
+        }
+        self.tabs.push(tab);
+        self.update_tab_bar();
+        self.switch_tab(self.tabs.len() - 1)
+    }
+
+    fn update_tab_bar(&mut self) {
+        let header_height = self.tab_bar_height() + self.config.row_height + HIST_HEIGHT;
+        for tab in self.tabs.iter_mut() {
+            tab.viewport.header_height = header_height;
+        }
+        let labels: Vec<_> = self.tabs.iter().map(Tab::label).collect();
+        self.text_system.set_tabs(&labels);
+    }
+
+    fn switch_tab(&mut self, index: usize) -> bool {
+        let Some(tab) = self.tabs.get(index) else {
+            return false;
+        };
+        self.active = index;
+        self.panel = None;
+        self.last_click = None;
+        self.text_system.set_fields(tab.field_names());
+        let (skip, batches) = tab.data.page();
+        self.text_system.update_buffers(batches, skip);
+        self.show_changes();
+        true
+    }
+
+    fn is_editing(&self) -> bool {
+        self.editor.is_some()
+            && self
+                .panel
+                .as_ref()
+                .is_some_and(|p| p.kind == PanelKind::Edit)
+    }
+
+    fn start_edit(&mut self) -> bool {
+        let tab = &self.tabs[self.active];
+        let Some((row, col)) = tab.selected else {
+            return false;
+        };
+        let Some((array, index)) = tab.data.value_at(row, col) else {
+            return false;
+        };
+        if array.data_type().is_nested() {
+            return false;
+        }
+        self.editor = Some(Editor::new(array, index, row, col));
+        self.show_editor();
+        true
+    }
+
+    fn type_into_editor(&mut self, event: &KeyEvent) -> bool {
+        let Some(editor) = self.editor.as_mut() else {
+            return false;
+        };
+        match &event.logical_key {
+            Key::Named(NamedKey::Escape) => {
+                self.editor = None;
+                self.panel = None;
+                return true;
+            }
+            Key::Named(NamedKey::Enter) => {
+                let request = self.editor.take().unwrap().request();
+                self.panel = None;
+                return self.tabs[self.active].query_tx.try_send(request).is_ok();
+            }
+            Key::Named(NamedKey::Backspace) => {
+                editor.text.pop();
+            }
+            _ => match &event.text {
+                Some(text) if !self.modifiers.control_key() => {
+                    editor.text.extend(text.chars().filter(|c| !c.is_control()))
+                }
+                _ => return false,
+            },
+        }
+        self.show_editor();
+        true
+    }
+
+    fn show_editor(&mut self) {
+        let Some(editor) = &self.editor else {
+            return;
+        };
+        let title = editor.title(&self.tabs[self.active].columns);
+        let mut blocks = vec![(title, format!("\n{}▏", editor.text))];
+        if let Some(error) = &editor.error {
+            blocks.push(("not kept".to_string(), format!("\n{error}")));
+        }
+        let help = match editor.target {
+            Target::Cell { .. } => {
+                "Enter keeps the value and Esc cancels. An empty value is null.".to_string()
+            }
+            Target::Column => "Enter adds a column computed with a sql expression, such as \
+                 `outlet - inlet as delta`. Esc cancels."
+                .to_string(),
+            Target::Group => {
+                let drill = match self.config.key(Action::Drill) {
+                    Some(key) => format!(
+                        " {key} opens the rows of the selected group and Backspace goes back."
+                    ),
+                    None => String::new(),
+                };
+                format!(
+                    "Enter groups the rows, such as `sum(amount), p90(price) by region pivot \
+                     month`.{drill} Esc cancels."
+                )
+            }
+        };
+        blocks.push((String::new(), help));
+        self.panel = Some(Panel::new(
+            &mut self.text_system.font_system,
+            self.text_system.metrics,
+            PanelKind::Edit,
+            &blocks,
+        ));
+    }
+
+    // asks every tab's worker what its table is set up with, the view is saved once they answer
+    fn start_saving(&mut self) -> bool {
+        self.saving = Some(vec![None; self.tabs.len()]);
+        for tab in &self.tabs {
+            if tab.query_tx.try_send(Request::Setup).is_err() {
+                // the answers of the others are let go
+                self.saving = None;
+                self.show_notice("a tab is busy, the view wasn't saved".to_string());
+                return true;
+            }
+        }
+        false
+    }
+
+    fn save_session(&mut self, setups: Vec<Option<Setup>>) {
+        let saved = |tab: &&Tab| session::can_save(&tab.path);
+        let active = self.tabs[..self.active].iter().filter(saved).count();
+        let tabs: Vec<_> = self
+            .tabs
+            .iter()
+            .zip(setups)
+            .filter(|(tab, _)| saved(tab))
+            .map(|(tab, setup)| SavedTab {
+                path: tab.path.clone(),
+                options: tab.options.clone(),
+                setup: setup.unwrap_or_default(),
+                place: tab.place(),
+            })
+            .collect();
+        let file = session::file_for(self.session.as_deref(), &self.tabs[0].title);
+        let active = active.min(tabs.len().saturating_sub(1));
+        let message = match session::save(&file, &Session { tabs, active }) {
+            Ok(()) => {
+                let message = format!("saved the view to {}", file.display());
+                self.session = Some(file);
+                message
+            }
+            Err(e) => e,
+        };
+        self.show_notice(message);
+    }
+
+    fn show_notice(&mut self, message: String) {
+        self.panel = Some(Panel::new(
+            &mut self.text_system.font_system,
+            self.text_system.metrics,
+            PanelKind::Notice,
+            &[(message, String::new())],
+        ));
+    }
+
+    // a reload that changed the columns says so once, in the panel
+    fn show_changes(&mut self) {
+        let tab = &mut self.tabs[self.active];
+        if tab.changes.is_empty() {
+            return;
+        }
+        let changes = std::mem::take(&mut tab.changes);
+        self.panel = Some(Panel::new(
+            &mut self.text_system.font_system,
+            self.text_system.metrics,
+            PanelKind::Notice,
+            &schema::notice(&tab.title, &changes),
+        ));
+    }
+
+    // an edited config lays the tabs out again, and sets their text anew
+    fn apply_config(&mut self, config: Arc<Config>) {
+        self.text_system.configure(&config);
+        self.line_pipeline
+            .set_highlights(&self.queue, &config.theme.highlights);
+        for tab in self.tabs.iter_mut() {
+            tab.viewport
+                .set_cell_size(config.row_height, config.column_width);
+        }
+        self.config = config;
+        self.update_tab_bar();
+        let tab = &self.tabs[self.active];
+        self.text_system.set_fields(tab.field_names());
+        let (skip, batches) = tab.data.page();
+        self.text_system.update_buffers(batches, skip);
     }
 
     pub(crate) fn prepare(&mut self) {
-        if let Ok(batches) = self.results_rx.try_recv() {
-            self.text_system.update_buffers(&batches, self.last_update);
+        let config = config::get();
+        if !Arc::ptr_eq(&config, &self.config) {
+            self.apply_config(config);
         }
-        let current_line = self.offsets.y / -14.;
-        if (current_line - self.last_update as f32).abs() > 50. {
-            dbg!(self.offsets.y, current_line, self.last_update);
-            self.query_tx.blocking_send(current_line as usize).unwrap();
-            self.last_update = current_line as usize;
+        self.add_opened_tabs();
+        let page_size = self.config.page_size;
+        for i in 0..self.tabs.len() {
+            while let Ok(response) = self.tabs[i].results_rx.try_recv() {
+                self.handle_response(i, response);
+            }
         }
+        let tab = &mut self.tabs[self.active];
+        if let Some(start) = tab.data.request(&tab.viewport, page_size) {
+            tab.request_rows(start);
+        }
+        let tab = &self.tabs[self.active];
+        let header_top = self.tab_bar_height();
+        let (row_height, col_width) = (self.config.row_height, self.config.column_width);
+        let mut instances: Vec<_> = tab
+            .distributions
+            .iter()
+            .enumerate()
+            .filter_map(|(j, d)| {
+                let left = column_x(&tab.viewport, &tab.column_map, j)?;
+                d.as_ref()
+                    .map(|d| d.instances(left, header_top + row_height, col_width - 10.))
+            })
+            .flatten()
+            .collect();
+        if self.tabs.len() > 1 {
+            // instances live in grid space, the tab bar doesn't scroll with it
+            instances.push(line::Instance::new(
+                (self.active as f32 * TAB_WIDTH - tab.viewport.x, 0.),
+                (TAB_WIDTH - 4., row_height),
+                60.,
+            ));
+        }
+        // edited cells are tinted, lighter than the selection
+        for &(row, col) in &tab.edited {
+            let top = tab.viewport.cell_top(row);
+            let Some(left) = column_x(&tab.viewport, &tab.column_map, col) else {
+                continue;
+            };
+            if tab.viewport.visible_rows().contains(&row) && top >= tab.viewport.header_height {
+                instances.push(
+                    line::Instance::new((left - 2., top), (col_width - 6., row_height), 25.)
+                        .highlight(Highlight::Edited),
+                );
+            }
+        }
+        if let Some((row, col)) = tab.selected {
+            let top = tab.viewport.cell_top(row);
+            let left = column_x(&tab.viewport, &tab.column_map, col);
+            if let Some(left) = left.filter(|_| top >= tab.viewport.header_height) {
+                instances.push(
+                    line::Instance::new((left - 2., top), (col_width - 6., row_height), 60.)
+                        .highlight(Highlight::Selection),
+                );
+            }
+        }
+        let (width, height) = (self.target.width(), self.target.height());
+        let camera = Camera {
+            x: tab.viewport.x,
+            y: 0.,
+            width: width as f32,
+            height: height as f32,
+        };
+        self.line_pipeline
+            .prepare(&self.device, &self.queue, &camera, &instances);
         self.text_system.prepare(
             &self.device,
             &self.queue,
-            self.config.width,
-            self.config.height,
-            self.offsets,
+            width,
+            height,
+            &self.tabs[self.active].viewport,
+            &self.tabs[self.active].column_map,
+            header_top,
+            self.grid_right(),
+            self.panel.as_ref().map_or(vec![], |p| {
+                p.text_areas(width, height, self.text_system.colors.panel)
+            }),
         );
     }
+
+    // background tabs keep their data up to date, only the active one has text buffers
+    fn handle_response(&mut self, index: usize, response: Response) {
+        let active = index == self.active;
+        let tab = &mut self.tabs[index];
+        match response {
+            Response::Rows(skip, batches) => {
+                if active {
+                    self.text_system.update_buffers(&batches, skip);
+                }
+                tab.data.load(skip, batches);
+            }
+            Response::Columns(columns) => {
+                tab.distributions = vec![None; columns.len()];
+                tab.column_map.update(&tab.columns, &columns);
+                tab.columns = columns;
+                tab.arrange();
+                let shown = |col| tab.column_map.position(col).is_some();
+                tab.selected = tab.selected.filter(|&(_, col)| shown(col));
+                if active {
+                    self.text_system.set_fields(tab.field_names());
+                    self.panel = None;
+                }
+                tab.request_rows(tab.data.requested());
+            }
+            Response::Distribution(column, distribution) => {
+                if let Some(slot) = tab.distributions.get_mut(column) {
+                    *slot = distribution;
+                }
+            }
+            Response::Profile(profile) => {
+                let kind = PanelKind::Profile(profile.column);
+                if active && self.panel.as_ref().is_some_and(|p| p.kind == kind) {
+                    let blocks = self.profile_blocks(profile.column, profile.blocks());
+                    self.panel = Some(Panel::new(
+                        &mut self.text_system.font_system,
+                        self.text_system.metrics,
+                        kind,
+                        &blocks,
+                    ));
+                }
+            }
+            Response::ProfileFailed(column, error) => {
+                let kind = PanelKind::Profile(column);
+                let name = tab.columns.get(column).map(|c| c.name.clone());
+                let open = self.panel.as_ref().is_some_and(|p| p.kind == kind);
+                if let Some(name) = name.filter(|_| active && open) {
+                    let blocks = self.profile_blocks(column, profile::failed_blocks(&name, &error));
+                    self.panel = Some(Panel::new(
+                        &mut self.text_system.font_system,
+                        self.text_system.metrics,
+                        kind,
+                        &blocks,
+                    ));
+                }
+            }
+            Response::Progress(progress) => {
+                // scrolling up stops following the end of the file
+                let page = ((tab.viewport.height - tab.viewport.header_height).max(0.)
+                    / tab.viewport.row_height) as usize;
+                let rows = tab.progress.map_or(0, |p| p.rows);
+                if tab.follow && tab.viewport.first_row() + page >= rows {
+                    tab.viewport
+                        .scroll_to_row(progress.rows.saturating_sub(page));
+                }
+                tab.progress = Some(progress);
+                self.update_tab_bar();
+            }
+            Response::Edited(cells) => tab.edited = cells,
+            // the rows are others now, so the view starts over at the top
+            Response::Viewing(viewing) => {
+                tab.viewing = viewing;
+                tab.viewport.scroll_to_row(0);
+                tab.data.request_from(0);
+                tab.selected = None;
+                tab.edited.clear();
+                if active {
+                    self.panel = None;
+                }
+                self.update_tab_bar();
+            }
+            Response::EditFailed(editor) => {
+                if active {
+                    self.editor = Some(editor);
+                    self.show_editor();
+                }
+            }
+            Response::Message(message) => {
+                if active {
+                    self.show_notice(message);
+                } else {
+                    log::info!("{message}");
+                }
+            }
+            Response::Setup(setup) => {
+                let Some(setups) = &mut self.saving else {
+                    return;
+                };
+                setups[index] = Some(setup);
+                if setups.iter().all(Option::is_some) {
+                    let setups = self.saving.take().unwrap();
+                    self.save_session(setups);
+                }
+            }
+            Response::Reloaded(changes) => {
+                tab.changes = changes;
+                if active {
+                    self.show_changes();
+                }
+            }
+            Response::Cast { column, failed } => {
+                tab.cast_report = Some((column, failed));
+                if active {
+                    self.open_profile(column);
+                }
+            }
+        }
+    }
 }
 
-#[derive(Default, Clone, Copy)]
-struct Offsets {
-    x: f32,
-    y: f32,
+// an empty grid laid out with the configured cell size, the tab bar sets its header
+fn grid_viewport(config: &Config, width: f32, height: f32) -> Viewport {
+    let mut viewport = Viewport::new(width, height, 0., 0);
+    viewport.set_cell_size(config.row_height, config.column_width);
+    viewport
 }
 
-impl Offsets {
-    fn update(&mut self, x_delta: f32, y_delta: f32, speed: f32) {
-        self.y += speed * y_delta * y_delta * y_delta.signum();
-        self.x += speed * x_delta * x_delta * x_delta.signum();
-        // dbg!(self.y);
-        self.y = self.y.min(0.);
-        self.x = self.x.min(0.);
-    }
+// where a column's lines go in grid space, nowhere when it is hidden or under the pinned ones
+fn column_x(viewport: &Viewport, column_map: &ColumnMap, col: usize) -> Option<f32> {
+    let position = column_map.position(col)?;
+    let left = viewport.cell_left(position);
+    (position < viewport.pinned || left >= viewport.pinned_width()).then_some(left - viewport.x)
+}
+
+async fn request_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::default(),
+                label: None,
+            },
+            None,
+        )
+        .await
+        .unwrap()
 }
 
 struct TextSystem {
@@ -260,53 +1270,106 @@ struct TextSystem {
     swash_cache: SwashCache,
     atlas: TextAtlas,
     metrics: Metrics,
+    // where a cell's text wraps
+    cell_width: f32,
+    colors: TextColors,
     renderer: TextRenderer,
+    tab_buffers: Vec<glyphon::Buffer>,
     field_buffers: Vec<Cell>,
     buffers: Vec<Cell>,
 }
 
+struct TextColors {
+    tab: glyphon::Color,
+    header: glyphon::Color,
+    cell: glyphon::Color,
+    panel: glyphon::Color,
+}
+
+impl TextColors {
+    fn new(theme: &Theme) -> Self {
+        let color = |[r, g, b]: [u8; 3]| glyphon::Color::rgb(r, g, b);
+        Self {
+            tab: color(theme.tab_text),
+            header: color(theme.header_text),
+            cell: color(theme.cell_text),
+            panel: color(theme.panel_text),
+        }
+    }
+}
+
 impl TextSystem {
     fn new(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         format: TextureFormat,
+        config: &Config,
         field_names: Vec<String>,
     ) -> Self {
-        let mut font_system = FontSystem::new();
+        let font_system = FontSystem::new();
         let swash_cache = SwashCache::new();
 
         let mut atlas = TextAtlas::new(device, queue, format);
-        let metrics = Metrics::new(12.0, 14.);
+        let metrics = Metrics::new(config.font_size, config.row_height);
         let renderer = TextRenderer::new(&mut atlas, device, MultisampleState::default(), None);
 
-        let field_buffers = field_names
+        let mut text_system = Self {
+            font_system,
+            swash_cache,
+            atlas,
+            metrics,
+            cell_width: config.column_width - 10.,
+            colors: TextColors::new(&config.theme),
+            renderer,
+            tab_buffers: vec![],
+            field_buffers: vec![],
+            buffers: vec![],
+        };
+        text_system.set_fields(field_names);
+        text_system
+    }
+
+    // the buffers made from here on take it, the caller sets the text again
+    fn configure(&mut self, config: &Config) {
+        self.metrics = Metrics::new(config.font_size, config.row_height);
+        self.cell_width = config.column_width - 10.;
+        self.colors = TextColors::new(&config.theme);
+    }
+
+    fn set_fields(&mut self, field_names: Vec<String>) {
+        self.field_buffers = field_names
             .into_iter()
             .enumerate()
             .map(|(j, f)| {
-                let mut buffer = glyphon::Buffer::new(&mut font_system, metrics);
-                let mut buffer_bor = buffer.borrow_with(&mut font_system);
-                buffer_bor.set_size(100., 14.);
+                let mut buffer = glyphon::Buffer::new(&mut self.font_system, self.metrics);
+                let mut buffer_bor = buffer.borrow_with(&mut self.font_system);
+                buffer_bor.set_size(self.cell_width, self.metrics.line_height);
                 buffer_bor.set_wrap(glyphon::Wrap::Glyph);
                 buffer_bor.set_text(&f, Attrs::new(), glyphon::Shaping::Advanced);
                 Cell::new(j, 0, buffer)
             })
             .collect();
+    }
 
-        Self {
-            font_system,
-            swash_cache,
-            atlas,
-            metrics,
-            renderer,
-            field_buffers,
-            buffers: vec![],
-        }
+    fn set_tabs(&mut self, titles: &[String]) {
+        self.tab_buffers = titles
+            .iter()
+            .map(|title| {
+                let mut buffer = glyphon::Buffer::new(&mut self.font_system, self.metrics);
+                let mut buffer_bor = buffer.borrow_with(&mut self.font_system);
+                buffer_bor.set_size(TAB_WIDTH - 10., self.metrics.line_height);
+                buffer_bor.set_wrap(glyphon::Wrap::Glyph);
+                buffer_bor.set_text(title, Attrs::new(), glyphon::Shaping::Advanced);
+                buffer
+            })
+            .collect();
     }
 
     fn update_buffers(&mut self, batches: &[RecordBatch], skip: usize) {
         let now = Instant::now();
         let format_options = FormatOptions::default();
         let mut cells = Vec::new();
+        let mut first_row = skip;
         // for (j, field) in batches[0].schema().fields().iter().enumerate() {
         //     let mut buffer = glyphon::Buffer::new(&mut self.font_system, self.metrics);
         //     let mut buffer_bor = buffer.borrow_with(&mut self.font_system);
@@ -325,65 +1388,109 @@ impl TextSystem {
 
             for row in 0..batch.num_rows() {
                 for (j, formatter) in formatters.iter().enumerate() {
-                    let val = formatter.value(row);
+                    let val = nested::summary(batch.column(j).as_ref(), row)
+                        .unwrap_or_else(|| formatter.value(row).to_string());
                     let mut buffer = glyphon::Buffer::new(&mut self.font_system, self.metrics);
                     let mut buffer_bor = buffer.borrow_with(&mut self.font_system);
-                    buffer_bor.set_size(100., 14.);
+                    buffer_bor.set_size(self.cell_width, self.metrics.line_height);
                     buffer_bor.set_wrap(glyphon::Wrap::Glyph);
-                    buffer_bor.set_text(&val.to_string(), Attrs::new(), glyphon::Shaping::Advanced);
-                    cells.push(Cell::new(j, row + 1 + skip, buffer));
+                    buffer_bor.set_text(&val, Attrs::new(), glyphon::Shaping::Advanced);
+                    cells.push(Cell::new(j, first_row + row, buffer));
                 }
             }
+            first_row += batch.num_rows();
         }
         println!("buffers took: {:?}", now.elapsed());
 
         self.buffers = cells;
     }
 
-    fn prepare(
-        &mut self,
+    #[allow(clippy::too_many_arguments)]
+    fn prepare<'b>(
+        &'b mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         width: u32,
         height: u32,
-        offsets: Offsets,
+        viewport: &Viewport,
+        column_map: &ColumnMap,
+        header_top: f32,
+        grid_right: f32,
+        overlay: Vec<TextArea<'b>>,
     ) {
-        let mut areas: Vec<_> = self
-            .field_buffers
+        let (rows, cols) = (viewport.visible_rows(), viewport.visible_columns());
+        let tabs = if header_top > 0. {
+            &self.tab_buffers[..]
+        } else {
+            &[]
+        };
+        let mut areas: Vec<_> = tabs
             .iter()
-            .map(|c| TextArea {
-                buffer: &c.buffer,
-                left: offsets.x + (c.col as f32 * 110.),
+            .enumerate()
+            .map(|(i, buffer)| TextArea {
+                buffer,
+                left: i as f32 * TAB_WIDTH + 4.,
                 top: 0.,
                 scale: 1.0,
                 bounds: TextBounds {
                     left: 0,
                     top: 0,
-                    right: i32::MAX,
-                    bottom: i32::MAX,
+                    right: grid_right as i32,
+                    bottom: header_top as i32,
                 },
-                default_color: glyphon::Color::rgb(180, 180, 180),
+                default_color: self.colors.tab,
             })
             .collect();
-
-        let cell_areas: Vec<_> = self
-            .buffers
-            .iter()
-            .map(|c| TextArea {
+        // scrolled columns go under the pinned ones
+        let shown = |col| {
+            let position = column_map.position(col).filter(|p| cols.contains(p))?;
+            let left = match position < viewport.pinned {
+                true => 0,
+                false => viewport.pinned_width() as i32,
+            };
+            Some((viewport.cell_left(position), left))
+        };
+        let field_areas = self.field_buffers.iter().filter_map(|c| {
+            let (x, left) = shown(c.col)?;
+            Some(TextArea {
                 buffer: &c.buffer,
-                left: offsets.x + (c.col as f32 * 110.),
-                top: offsets.y + (c.row as f32 * 14.),
+                left: x,
+                top: header_top,
                 scale: 1.0,
                 bounds: TextBounds {
-                    left: 0,
-                    top: 14,
-                    right: i32::MAX,
+                    left,
+                    top: 0,
+                    right: grid_right as i32,
                     bottom: i32::MAX,
                 },
-                default_color: glyphon::Color::rgb(240, 240, 255),
+                default_color: self.colors.header,
+            })
+        });
+        areas.extend(field_areas);
+
+        let cell_areas: Vec<_> = self
+            .buffers
+            .iter()
+            .filter(|c| rows.contains(&c.row))
+            .filter_map(|c| {
+                let (x, left) = shown(c.col)?;
+                Some(TextArea {
+                    buffer: &c.buffer,
+                    left: x,
+                    top: viewport.cell_top(c.row),
+                    scale: 1.0,
+                    bounds: TextBounds {
+                        left,
+                        top: viewport.header_height as i32,
+                        right: grid_right as i32,
+                        bottom: i32::MAX,
+                    },
+                    default_color: self.colors.cell,
+                })
             })
             .collect();
         areas.extend(cell_areas);
+        areas.extend(overlay);
         self.renderer
             .prepare(
                 device,
@@ -0,0 +1,238 @@
+use std::path::Path;
+
+use datafusion::prelude::SessionContext;
+use tokio::sync::{
+    mpsc,
+    oneshot::{self, error::TryRecvError},
+};
+
+use crate::{
+    histogram::Distribution,
+    pipe::{self, Progress},
+    postgres,
+    schema::ColumnInfo,
+    session::{Place, Setup},
+    source::{self, FileOptions},
+    viewport::{ColumnMap, DataWindow, Hit, Viewport},
+    worker::{spawn_worker, Notify, Opened, Request, Response},
+};
+
+pub(crate) const TAB_WIDTH: f32 = 160.;
+
+// one open file with its own table, worker and scroll position
+pub(crate) struct Tab {
+    pub(crate) title: String,
+    pub(crate) table: String,
+    // where the table comes from and how it's read, to save the view
+    pub(crate) path: String,
+    pub(crate) options: FileOptions,
+    pub(crate) viewport: Viewport,
+    pub(crate) data: DataWindow,
+    pub(crate) query_tx: mpsc::Sender<Request>,
+    pub(crate) results_rx: mpsc::Receiver<Response>,
+    pub(crate) columns: Vec<ColumnInfo>,
+    pub(crate) distributions: Vec<Option<Distribution>>,
+    pub(crate) selected: Option<(usize, usize)>,
+    // column and number of values that failed to parse in the last cast
+    pub(crate) cast_report: Option<(usize, usize)>,
+    // rows read so far, for a pipe
+    pub(crate) progress: Option<Progress>,
+    // a followed file keeps its last row in view while it grows
+    pub(crate) follow: bool,
+    // column changes from reloading the file, shown once the tab is active
+    pub(crate) changes: Vec<String>,
+    // cells holding an edit, drawn highlighted
+    pub(crate) edited: Vec<(usize, usize)>,
+    // what is shown instead of the table's rows, a grouping or the rows of one group
+    pub(crate) viewing: Option<String>,
+    // the columns shown, in the order they are shown
+    pub(crate) column_map: ColumnMap,
+}
+
+// a tab whose worker is still opening its file, so the window doesn't wait on a big one
+pub(crate) struct Opening {
+    pub(crate) table: String,
+    path: String,
+    options: FileOptions,
+    query_tx: mpsc::Sender<Request>,
+    results_rx: mpsc::Receiver<Response>,
+    opened_rx: oneshot::Receiver<Opened>,
+}
+
+impl Opening {
+    pub(crate) fn new(
+        ctx: &SessionContext,
+        path: String,
+        options: FileOptions,
+        setup: Option<Setup>,
+        taken: &[&str],
+        notify: Notify,
+    ) -> Self {
+        let table = source::table_name(&path, taken);
+        let (query_tx, results_rx, opened_rx) = spawn_worker(
+            ctx.clone(),
+            table.clone(),
+            path.clone(),
+            options.clone(),
+            setup,
+            notify,
+        );
+        Self {
+            table,
+            path,
+            options,
+            query_tx,
+            results_rx,
+            opened_rx,
+        }
+    }
+
+    // what the worker made of the file, once it has
+    pub(crate) fn opened(&mut self) -> Option<Opened> {
+        match self.opened_rx.try_recv() {
+            Ok(opened) => Some(opened),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Closed) => Some(Err(format!("could not open {}", self.path))),
+        }
+    }
+}
+
+impl Tab {
+    pub(crate) async fn open(
+        ctx: &SessionContext,
+        path: String,
+        options: FileOptions,
+        setup: Option<Setup>,
+        taken: &[&str],
+        notify: Notify,
+        viewport: Viewport,
+    ) -> Result<Self, String> {
+        let mut opening = Opening::new(ctx, path, options, setup, taken, notify);
+        let opened = (&mut opening.opened_rx)
+            .await
+            .unwrap_or_else(|_| Err(format!("could not open {}", opening.path)));
+        Self::new(opening, opened, viewport)
+    }
+
+    pub(crate) fn new(
+        opening: Opening,
+        opened: Opened,
+        viewport: Viewport,
+    ) -> Result<Self, String> {
+        let Opening {
+            table,
+            path,
+            options,
+            query_tx,
+            results_rx,
+            ..
+        } = opening;
+        let (columns, viewing) = opened?;
+        let follow = pipe::is_followed(&path, &options);
+        query_tx
+            .try_send(Request::Rows(0))
+            .map_err(|_| format!("could not read {path}"))?;
+        // a url may hold a password, so it isn't shown
+        let title = match postgres::split(&path) {
+            Some((_, table)) => table.unwrap_or("postgres").to_string(),
+            None if path == "-" => "stdin".to_string(),
+            None => Path::new(source::root(&path))
+                .file_name()
+                .map_or(path.clone(), |f| f.to_string_lossy().into_owned()),
+        };
+        Ok(Self {
+            title,
+            table,
+            path: path.clone(),
+            options,
+            viewport: Viewport {
+                ncols: columns.len(),
+                ..viewport
+            },
+            data: DataWindow::default(),
+            query_tx,
+            results_rx,
+            distributions: vec![None; columns.len()],
+            column_map: ColumnMap::new(columns.len()),
+            columns,
+            selected: None,
+            cast_report: None,
+            progress: (pipe::is_pipe(&path) || follow).then(Progress::default),
+            follow,
+            changes: vec![],
+            edited: vec![],
+            viewing,
+        })
+    }
+
+    pub(crate) fn label(&self) -> String {
+        let title = match &self.viewing {
+            Some(viewing) => format!("{}: {viewing}", self.title),
+            None => self.title.clone(),
+        };
+        match self.progress {
+            Some(Progress { rows, done: false }) => format!("{title} ({rows} rows...)"),
+            Some(Progress { rows, done: true }) => format!("{title} ({rows} rows)"),
+            None => title,
+        }
+    }
+
+    // the viewport lays out the columns the map shows
+    pub(crate) fn arrange(&mut self) {
+        self.viewport.ncols = self.column_map.len();
+        self.viewport.pinned = self.column_map.pinned();
+    }
+
+    // hits land on positions of the view, they are told by the query column there
+    pub(crate) fn hit(&self, x: f32, y: f32) -> Option<Hit> {
+        let column = |position| self.column_map.column(position);
+        match self.viewport.hit(x, y)? {
+            Hit::Header(position) => Some(Hit::Header(column(position)?)),
+            Hit::Cell { row, col } => Some(Hit::Cell {
+                row,
+                col: column(col)?,
+            }),
+        }
+    }
+
+    // where the view is, to save it
+    pub(crate) fn place(&self) -> Place {
+        let (order, hidden) = self.column_map.names(&self.columns);
+        Place {
+            order,
+            hidden,
+            pinned: self.column_map.pinned(),
+            row: self.viewport.first_row(),
+            column: self.viewport.first_column(),
+            selected: self
+                .selected
+                .map(|(row, col)| (row, self.columns[col].name.clone())),
+        }
+    }
+
+    // puts the view back where it was saved
+    pub(crate) fn go_to(&mut self, place: &Place) {
+        self.column_map =
+            ColumnMap::restore(&self.columns, &place.order, &place.hidden, place.pinned);
+        self.arrange();
+        self.viewport.scroll_to_row(place.row);
+        self.viewport.scroll_to_column(place.column);
+        self.selected = place.selected.as_ref().and_then(|(row, name)| {
+            let col = self.columns.iter().position(|c| c.name == *name)?;
+            self.column_map.position(col).map(|_| (*row, col))
+        });
+        let start = self.data.request_from(place.row);
+        self.request_rows(start);
+    }
+
+    // a busy worker is asked again on the next frame
+    pub(crate) fn request_rows(&mut self, start: usize) {
+        if self.query_tx.try_send(Request::Rows(start)).is_err() {
+            self.data.refetch();
+        }
+    }
+
+    pub(crate) fn field_names(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+}
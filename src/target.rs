@@ -0,0 +1,140 @@
+pub(crate) enum RenderTarget<'a> {
+    Surface {
+        surface: wgpu::Surface<'a>,
+        config: wgpu::SurfaceConfiguration,
+    },
+    Texture(wgpu::Texture),
+}
+
+pub(crate) struct Frame {
+    pub(crate) view: wgpu::TextureView,
+    surface_texture: Option<wgpu::SurfaceTexture>,
+}
+
+impl Frame {
+    pub(crate) fn present(self) {
+        if let Some(surface_texture) = self.surface_texture {
+            surface_texture.present();
+        }
+    }
+}
+
+impl<'a> RenderTarget<'a> {
+    pub(crate) fn texture(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        Self::Texture(device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        }))
+    }
+
+    pub(crate) fn format(&self) -> wgpu::TextureFormat {
+        match self {
+            Self::Surface { config, .. } => config.format,
+            Self::Texture(texture) => texture.format(),
+        }
+    }
+
+    pub(crate) fn width(&self) -> u32 {
+        match self {
+            Self::Surface { config, .. } => config.width,
+            Self::Texture(texture) => texture.width(),
+        }
+    }
+
+    pub(crate) fn height(&self) -> u32 {
+        match self {
+            Self::Surface { config, .. } => config.height,
+            Self::Texture(texture) => texture.height(),
+        }
+    }
+
+    pub(crate) fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        match self {
+            Self::Surface { surface, config } => {
+                config.width = width;
+                config.height = height;
+                surface.configure(device, config);
+            }
+            Self::Texture(_) => *self = Self::texture(device, width, height),
+        }
+    }
+
+    pub(crate) fn frame(&self) -> Result<Frame, wgpu::SurfaceError> {
+        match self {
+            Self::Surface { surface, .. } => {
+                let surface_texture = surface.get_current_texture()?;
+                let view = surface_texture
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                Ok(Frame {
+                    view,
+                    surface_texture: Some(surface_texture),
+                })
+            }
+            Self::Texture(texture) => Ok(Frame {
+                view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                surface_texture: None,
+            }),
+        }
+    }
+
+    pub(crate) fn read_rgba(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Vec<u8>> {
+        let Self::Texture(texture) = self else {
+            return None;
+        };
+        let (width, height) = (texture.width(), texture.height());
+        // rows of a texture copy have to be padded to a multiple of 256 bytes
+        let unpadded_bytes_per_row = width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| tx.send(result).unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        rx.recv().ok()?.ok()?;
+
+        let data = slice.get_mapped_range();
+        let rgba = data
+            .chunks(padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+            .copied()
+            .collect();
+        drop(data);
+        buffer.unmap();
+        Some(rgba)
+    }
+}
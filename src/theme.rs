@@ -0,0 +1,119 @@
+// the colors everything is drawn with, as they show on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Theme {
+    pub(crate) background: [u8; 3],
+    pub(crate) tab_text: [u8; 3],
+    pub(crate) header_text: [u8; 3],
+    pub(crate) cell_text: [u8; 3],
+    pub(crate) panel_text: [u8; 3],
+    pub(crate) highlights: Highlights,
+}
+
+// what the line pipeline tints, each at its own strength
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Highlights {
+    // distribution plots and the active tab
+    pub(crate) plot: [u8; 3],
+    pub(crate) selection: [u8; 3],
+    pub(crate) edited: [u8; 3],
+}
+
+const THEMES: [(&str, Theme); 3] = [
+    (
+        "dark",
+        Theme {
+            background: [0, 16, 69],
+            tab_text: [200, 200, 220],
+            header_text: [180, 180, 180],
+            cell_text: [240, 240, 255],
+            panel_text: [200, 200, 220],
+            highlights: Highlights {
+                plot: [170, 182, 234],
+                selection: [170, 182, 234],
+                edited: [170, 182, 234],
+            },
+        },
+    ),
+    (
+        "light",
+        Theme {
+            background: [248, 248, 244],
+            tab_text: [48, 48, 74],
+            header_text: [80, 80, 80],
+            cell_text: [16, 16, 24],
+            panel_text: [48, 48, 74],
+            highlights: Highlights {
+                plot: [59, 91, 219],
+                selection: [59, 91, 219],
+                edited: [59, 91, 219],
+            },
+        },
+    ),
+    (
+        "high_contrast",
+        Theme {
+            background: [0, 0, 0],
+            tab_text: [255, 255, 255],
+            header_text: [255, 255, 0],
+            cell_text: [255, 255, 255],
+            panel_text: [255, 255, 255],
+            highlights: Highlights {
+                plot: [0, 255, 255],
+                selection: [255, 255, 0],
+                edited: [255, 0, 255],
+            },
+        },
+    ),
+];
+
+// told apart by more than red and green, in place of a theme's own highlights
+const PALETTES: [(&str, Highlights); 2] = [
+    // Okabe and Ito's sky blue, orange and bluish green
+    (
+        "okabe_ito",
+        Highlights {
+            plot: [86, 180, 233],
+            selection: [230, 159, 0],
+            edited: [0, 158, 115],
+        },
+    ),
+    // Paul Tol's bright blue, yellow and purple
+    (
+        "tol",
+        Highlights {
+            plot: [68, 119, 170],
+            selection: [204, 187, 68],
+            edited: [170, 51, 119],
+        },
+    ),
+];
+
+impl Default for Theme {
+    fn default() -> Self {
+        THEMES[0].1
+    }
+}
+
+impl Theme {
+    pub(crate) fn named(name: &str) -> Option<Self> {
+        THEMES.iter().find(|(n, _)| *n == name).map(|(_, t)| *t)
+    }
+
+    pub(crate) fn palette(name: &str) -> Option<Highlights> {
+        PALETTES.iter().find(|(n, _)| *n == name).map(|(_, h)| *h)
+    }
+
+    pub(crate) fn clear_color(&self) -> wgpu::Color {
+        let [r, g, b] = self.background.map(linear);
+        wgpu::Color { r, g, b, a: 1.0 }
+    }
+}
+
+// the window is drawn in linear space
+pub(crate) fn linear(channel: u8) -> f64 {
+    let c = channel as f64 / 255.;
+    match c <= 0.04045 {
+        true => c / 12.92,
+        false => ((c + 0.055) / 1.055).powf(2.4),
+    }
+}
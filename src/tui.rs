@@ -0,0 +1,602 @@
+use std::{io, path::PathBuf, sync::mpsc as std_mpsc, thread, time::Duration};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use datafusion::arrow::{
+    array::Array,
+    datatypes::DataType,
+    util::display::{ArrayFormatter, FormatOptions},
+};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph, Row, Table, Wrap},
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    config::{self, Action},
+    detail,
+    edit::Editor,
+    nested,
+    panel::PanelKind,
+    pipe::{self, Progress},
+    profile,
+    schema::{self, ColumnInfo},
+    session::{self, Place, SavedTab, Session, Setup},
+    source::{self, FileOptions},
+    viewport::{ColumnMap, DataWindow, Viewport, COL_WIDTH, ROW_HEIGHT},
+    worker::{spawn_worker, Notify, Request, Response},
+};
+
+// terminal cells per grid column, the viewport itself keeps counting in pixels
+const CELL_WIDTH: u16 = 12;
+const PANEL_WIDTH: u16 = 40;
+
+struct Tui {
+    query_tx: mpsc::Sender<Request>,
+    results_rx: std_mpsc::Receiver<Response>,
+    viewport: Viewport,
+    data: DataWindow,
+    columns: Vec<ColumnInfo>,
+    // the columns shown, in the order they are shown
+    column_map: ColumnMap,
+    // row and position in the view, which stays put when the column there is hidden
+    selected: (usize, usize),
+    panel: Option<(PanelKind, Vec<(String, String)>)>,
+    panel_scroll: u16,
+    // rows read so far, for a pipe
+    progress: Option<Progress>,
+    // a followed file keeps the last row selected while it grows
+    follow: bool,
+    table: String,
+    edited: Vec<(usize, usize)>,
+    editor: Option<Editor>,
+    // a grouping, or the rows of one group, shown instead of the table's rows
+    viewing: Option<String>,
+    path: String,
+    options: FileOptions,
+    // the view file opened or last saved, which saving writes to again
+    session: Option<PathBuf>,
+}
+
+pub(crate) fn run(
+    path: String,
+    options: FileOptions,
+    session: Option<(PathBuf, Session)>,
+) -> io::Result<()> {
+    // the terminal shows one table, the one that was active in a view file
+    let (opened, path, options, saved) = match session {
+        Some((file, mut session)) => {
+            let tab = session.tabs.swap_remove(session.active);
+            (
+                Some(file),
+                tab.path,
+                tab.options,
+                Some((tab.setup, tab.place)),
+            )
+        }
+        None => (None, path, options, None),
+    };
+    let (setup, place) = saved.unzip();
+    let table = source::table_name(&path, &[]);
+    let follow = pipe::is_followed(&path, &options);
+    let (query_tx, results_rx, columns_rx) = spawn_worker(
+        source::context(),
+        table.clone(),
+        path.clone(),
+        options.clone(),
+        setup,
+        Notify::default(),
+    );
+    let (columns, viewing) = columns_rx
+        .blocking_recv()
+        .unwrap_or_else(|_| Err(format!("could not open {path}")))
+        .map_err(io::Error::other)?;
+    let mut tui = Tui {
+        query_tx,
+        results_rx: forward(results_rx),
+        viewport: Viewport::new(0., 0., ROW_HEIGHT, columns.len()),
+        data: DataWindow::default(),
+        column_map: ColumnMap::new(columns.len()),
+        columns,
+        selected: (0, 0),
+        panel: None,
+        panel_scroll: 0,
+        progress: None,
+        follow,
+        table,
+        edited: vec![],
+        editor: None,
+        viewing,
+        path,
+        options,
+        session: opened,
+    };
+    match place {
+        Some(place) => tui.go_to(&place),
+        None => {
+            let start = tui.data.request_from(0);
+            tui.send(Request::Rows(start));
+        }
+    }
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let result = Terminal::new(CrosstermBackend::new(io::stdout()))
+        .and_then(|mut terminal| tui.event_loop(&mut terminal));
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    result
+}
+
+// the worker waits while its results aren't taken, so they are taken as they come and the
+// terminal can wait to send a request without both waiting on each other
+fn forward(mut results_rx: mpsc::Receiver<Response>) -> std_mpsc::Receiver<Response> {
+    let (tx, rx) = std_mpsc::channel();
+    thread::spawn(move || {
+        while let Some(response) = results_rx.blocking_recv() {
+            if tx.send(response).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+impl Tui {
+    fn event_loop(&mut self, terminal: &mut Terminal<impl Backend>) -> io::Result<()> {
+        loop {
+            while let Ok(response) = self.results_rx.try_recv() {
+                self.handle_response(response);
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Some(start) = self.data.request(&self.viewport, config::get().page_size) {
+                self.send(Request::Rows(start));
+            }
+            // the worker can't wake us up, so poll for its results in between key presses
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn send(&self, request: Request) {
+        if self.query_tx.blocking_send(request).is_err() {
+            log::error!("data worker is gone");
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if let Some(editor) = &mut self.editor {
+            match key.code {
+                KeyCode::Esc => self.editor = None,
+                KeyCode::Enter => {
+                    let request = self.editor.take().unwrap().request();
+                    self.send(request);
+                }
+                KeyCode::Backspace => {
+                    editor.text.pop();
+                }
+                KeyCode::Char(c) => editor.text.push(c),
+                _ => {}
+            }
+            return true;
+        }
+        let (row, position) = self.selected;
+        let col = self.column_map.column(position).unwrap_or_default();
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        // the keys of the config go first, as in the window
+        if let KeyCode::Char(c) = key.code {
+            if let Some(action) = config::get().action(&c.to_string(), control) {
+                self.act(action);
+                return true;
+            }
+        }
+        let page = self.page_rows();
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Char('c') if control => return false,
+            KeyCode::Backspace if self.viewing.is_some() => self.send(Request::Back),
+            KeyCode::Up | KeyCode::Char('k') => self.select(row.saturating_sub(1), position),
+            KeyCode::Down | KeyCode::Char('j') => self.select(row + 1, position),
+            KeyCode::Left | KeyCode::Char('h') => self.select(row, position.saturating_sub(1)),
+            KeyCode::Right | KeyCode::Char('l') => self.select(row, position + 1),
+            KeyCode::PageUp => self.select(row.saturating_sub(page), position),
+            KeyCode::PageDown => self.select(row + page, position),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0, position),
+            KeyCode::Char('K') => self.panel_scroll = self.panel_scroll.saturating_sub(1),
+            KeyCode::Char('J') => self.panel_scroll += 1,
+            KeyCode::Enter => self.open_detail(row, col),
+            KeyCode::Char('i') => self.open_profile(col),
+            KeyCode::F(2) => {
+                if matches!(self.panel, Some((PanelKind::Schema, _))) {
+                    self.panel = None;
+                } else {
+                    self.open_panel(
+                        PanelKind::Schema,
+                        schema::blocks(&self.columns, &self.column_map),
+                    );
+                }
+            }
+            KeyCode::Esc => self.panel = None,
+            _ => {}
+        }
+        true
+    }
+
+    fn act(&mut self, action: Action) {
+        let (row, position) = self.selected;
+        let col = self.column_map.column(position).unwrap_or_default();
+        match action {
+            Action::Edit => {
+                if let Some((array, index)) = self.data.value_at(row, col) {
+                    if !array.data_type().is_nested() {
+                        self.editor = Some(Editor::new(array, index, row, col));
+                    }
+                }
+            }
+            Action::AddColumn => self.editor = Some(Editor::column()),
+            Action::Undo => self.send(Request::Undo),
+            Action::Redo => self.send(Request::Redo),
+            Action::SaveEdits => self.send(Request::Save),
+            Action::ExportSql => self.send(Request::ExportSql),
+            Action::Group => self.editor = Some(Editor::group()),
+            Action::Drill if self.viewing.is_some() => self.send(Request::Drill(row)),
+            Action::Drill => {}
+            Action::Promote => {
+                if let Some((PanelKind::Tree { col, .. }, _)) = self.panel {
+                    self.send(Request::Promote(col));
+                }
+            }
+            Action::Hide => self.arrange(|map| map.hide(col), position),
+            Action::ShowAll => self.arrange(ColumnMap::show_all, position),
+            Action::Pin => {
+                self.arrange(|map| map.toggle_pin(col), position);
+                // the column is followed to where it went
+                if let Some(position) = self.column_map.position(col) {
+                    self.select(row, position);
+                }
+            }
+            Action::MoveLeft if position > 0 => {
+                self.arrange(|map| map.move_to(col, position - 1), position - 1)
+            }
+            Action::MoveLeft => {}
+            Action::MoveRight => self.arrange(|map| map.move_to(col, position + 1), position + 1),
+            Action::SaveView => self.send(Request::Setup),
+        }
+    }
+
+    fn page_rows(&self) -> usize {
+        ((self.viewport.height - self.viewport.header_height).max(0.) / ROW_HEIGHT) as usize
+    }
+
+    fn select(&mut self, row: usize, col: usize) {
+        let col = col.min(self.column_map.len().saturating_sub(1));
+        self.selected = (row, col);
+
+        let first_row = self.viewport.first_row();
+        let page = self.page_rows().max(1);
+        if row < first_row {
+            self.viewport.scroll_to_row(row);
+        } else if row >= first_row + page {
+            self.viewport.scroll_to_row(row + 1 - page);
+        }
+        // pinned columns are always in view, the rest scroll into the room right of them
+        let first = self.viewport.first_column();
+        let ncols = (self.viewport.width / COL_WIDTH) as usize;
+        let room = ncols.saturating_sub(self.viewport.pinned).max(1);
+        if col < self.viewport.pinned {
+            return;
+        }
+        if col < first {
+            self.viewport.scroll_to_column(col);
+        } else if col >= first + room {
+            self.viewport.scroll_to_column(col + 1 - room);
+        }
+    }
+
+    // hides, moves or pins columns, then selects the position the change left
+    fn arrange(&mut self, change: impl FnOnce(&mut ColumnMap) -> bool, position: usize) {
+        if change(&mut self.column_map) {
+            self.viewport.ncols = self.column_map.len();
+            self.viewport.pinned = self.column_map.pinned();
+            self.select(self.selected.0, position);
+        }
+    }
+
+    // where the view is, to save it
+    fn place(&self) -> Place {
+        let (order, hidden) = self.column_map.names(&self.columns);
+        let (row, position) = self.selected;
+        Place {
+            order,
+            hidden,
+            pinned: self.column_map.pinned(),
+            row: self.viewport.first_row(),
+            column: self.viewport.first_column(),
+            selected: self
+                .column_map
+                .column(position)
+                .map(|col| (row, self.columns[col].name.clone())),
+        }
+    }
+
+    // puts the view back where it was saved
+    fn go_to(&mut self, place: &Place) {
+        self.column_map =
+            ColumnMap::restore(&self.columns, &place.order, &place.hidden, place.pinned);
+        self.viewport.ncols = self.column_map.len();
+        self.viewport.pinned = self.column_map.pinned();
+        self.viewport.scroll_to_row(place.row);
+        self.viewport.scroll_to_column(place.column);
+        let selected = place.selected.as_ref().and_then(|(row, name)| {
+            let col = self.columns.iter().position(|c| c.name == *name)?;
+            Some((*row, self.column_map.position(col)?))
+        });
+        self.selected = selected.unwrap_or((place.row, 0));
+        let start = self.data.request_from(place.row);
+        self.send(Request::Rows(start));
+    }
+
+    fn save_session(&mut self, setup: Setup) {
+        let tabs = match session::can_save(&self.path) {
+            true => vec![SavedTab {
+                path: self.path.clone(),
+                options: self.options.clone(),
+                setup,
+                place: self.place(),
+            }],
+            false => vec![],
+        };
+        let file = session::file_for(self.session.as_deref(), &self.table);
+        let message = match session::save(&file, &Session { tabs, active: 0 }) {
+            Ok(()) => {
+                let message = format!("saved the view to {}", file.display());
+                self.session = Some(file);
+                message
+            }
+            Err(e) => e,
+        };
+        self.open_panel(PanelKind::Notice, vec![(message, String::new())]);
+    }
+
+    fn open_panel(&mut self, kind: PanelKind, blocks: Vec<(String, String)>) {
+        self.panel = Some((kind, blocks));
+        self.panel_scroll = 0;
+    }
+
+    fn open_profile(&mut self, col: usize) {
+        if col >= self.columns.len() {
+            return;
+        }
+        self.send(Request::Profile(col));
+        let name = self.columns[col].name.clone();
+        self.open_panel(
+            PanelKind::Profile(col),
+            vec![(name, "\n  computing...".to_string())],
+        );
+    }
+
+    fn open_detail(&mut self, row: usize, col: usize) {
+        let Some((array, index)) = self.data.value_at(row, col) else {
+            return;
+        };
+        let title = format!("{}, row {}", self.columns[col].name, row + 1);
+        if array.data_type().is_nested() {
+            let mut body = nested::tree(array, index);
+            if matches!(array.data_type(), DataType::Struct(_)) {
+                if let Some(key) = config::get().key(Action::Promote) {
+                    body.push_str(&format!("\n\npress {key} to promote the fields to columns"));
+                }
+            }
+            self.open_panel(PanelKind::Tree { row, col }, vec![(title, body)]);
+        } else {
+            let text = detail::detail_text(array, index);
+            self.open_panel(
+                PanelKind::Detail { row, col },
+                vec![(title, String::new()), (String::new(), text)],
+            );
+        }
+    }
+
+    fn handle_response(&mut self, response: Response) {
+        match response {
+            Response::Rows(skip, batches) => self.data.load(skip, batches),
+            Response::Columns(columns) => {
+                self.column_map.update(&self.columns, &columns);
+                self.viewport.ncols = self.column_map.len();
+                self.viewport.pinned = self.column_map.pinned();
+                self.columns = columns;
+                self.panel = None;
+                self.select(self.selected.0, self.selected.1);
+                self.send(Request::Rows(self.data.requested()));
+            }
+            // sparklines are only drawn, and types only changed, in the window
+            Response::Distribution(..) | Response::Cast { .. } => {}
+            Response::Reloaded(changes) if !changes.is_empty() => {
+                let blocks = schema::notice(&self.table, &changes);
+                self.open_panel(PanelKind::Notice, blocks);
+            }
+            Response::Reloaded(_) => {}
+            Response::Edited(cells) => self.edited = cells,
+            Response::Setup(setup) => self.save_session(setup),
+            // the rows are others now, so the view starts over at the top
+            Response::Viewing(viewing) => {
+                self.viewing = viewing;
+                self.viewport.scroll_to_row(0);
+                self.data.request_from(0);
+                self.selected = (0, 0);
+                self.edited.clear();
+                self.panel = None;
+            }
+            Response::EditFailed(editor) => self.editor = Some(editor),
+            Response::Message(message) => {
+                self.open_panel(PanelKind::Notice, vec![(message, String::new())])
+            }
+            Response::Progress(progress) => {
+                // moving off the last row stops following the end of the file
+                let rows = self.progress.map_or(0, |p| p.rows);
+                if self.follow && self.selected.0 + 1 >= rows {
+                    self.select(progress.rows.saturating_sub(1), self.selected.1);
+                }
+                self.progress = Some(progress);
+            }
+            Response::Profile(profile) => {
+                let kind = PanelKind::Profile(profile.column);
+                if self.panel.as_ref().is_some_and(|(k, _)| *k == kind) {
+                    self.panel = Some((kind, profile.blocks()));
+                }
+            }
+            Response::ProfileFailed(column, error) => {
+                let kind = PanelKind::Profile(column);
+                let open = self.panel.as_ref().is_some_and(|(k, _)| *k == kind);
+                if let Some(info) = self.columns.get(column).filter(|_| open) {
+                    self.panel = Some((kind, profile::failed_blocks(&info.name, &error)));
+                }
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.size());
+        let [grid, panel] = if self.panel.is_some() {
+            Layout::horizontal([Constraint::Min(CELL_WIDTH), Constraint::Length(PANEL_WIDTH)])
+                .areas(main)
+        } else {
+            [main, Rect::default()]
+        };
+
+        self.viewport.resize(
+            (grid.width / CELL_WIDTH) as f32 * COL_WIDTH,
+            grid.height as f32 * ROW_HEIGHT,
+        );
+        let columns: Vec<_> = self
+            .viewport
+            .visible_columns()
+            .into_iter()
+            .filter_map(|position| Some((position, self.column_map.column(position)?)))
+            .collect();
+        let header = Row::new(
+            columns
+                .iter()
+                .map(|&(_, col)| self.columns[col].name.clone()),
+        )
+        .style(Style::new().fg(Color::Gray).bold());
+        let rows = self
+            .viewport
+            .visible_rows()
+            .take(self.page_rows())
+            .map(|row| {
+                Row::new(columns.iter().map(|&(position, col)| {
+                    let text = self
+                        .data
+                        .value_at(row, col)
+                        .map(|(array, index)| cell_text(array, index))
+                        .unwrap_or_default();
+                    let text = match self.edited.contains(&(row, col)) {
+                        true => Text::from(text).yellow(),
+                        false => Text::from(text),
+                    };
+                    if (row, position) == self.selected {
+                        text.reversed()
+                    } else {
+                        text
+                    }
+                }))
+            });
+        let widths = vec![Constraint::Length(CELL_WIDTH - 1); columns.len()];
+        frame.render_widget(Table::new(rows, widths).header(header), grid);
+
+        if let Some((_, blocks)) = &self.panel {
+            let lines: Vec<Line> = blocks
+                .iter()
+                .flat_map(|(title, body)| {
+                    std::iter::once(Line::from(title.as_str()).bold())
+                        .chain(body.lines().map(Line::from))
+                })
+                .collect();
+            let paragraph = Paragraph::new(lines)
+                .block(Block::new().borders(Borders::LEFT))
+                .wrap(Wrap { trim: false })
+                .scroll((self.panel_scroll, 0));
+            frame.render_widget(paragraph, panel);
+        }
+
+        let (row, position) = self.selected;
+        let column = self
+            .column_map
+            .column(position)
+            .map(|col| &self.columns[col]);
+        let of = match self.progress {
+            Some(Progress { rows, done: false }) => format!(" of {rows}..."),
+            Some(Progress { rows, done: true }) => format!(" of {rows}"),
+            None => String::new(),
+        };
+        // the keys the config binds, an unbound action isn't told about
+        let config = config::get();
+        let bound = |action, label| config.key(action).map(|key| format!("{key} {label}"));
+        let fixed = |hint: &str| Some(hint.to_string());
+        let hints =
+            |hints: Vec<Option<String>>| hints.into_iter().flatten().collect::<Vec<_>>().join("  ");
+        let status_line = match &self.editor {
+            Some(editor) => format!(
+                " {}: {}_  {}|  enter keep  esc cancel",
+                editor.title(&self.columns),
+                editor.text,
+                editor
+                    .error
+                    .as_ref()
+                    .map_or(String::new(), |e| format!("{e}  ")),
+            ),
+            None => match &self.viewing {
+                Some(viewing) => format!(
+                    " {viewing}  row {}{of}  |  {}",
+                    row + 1,
+                    hints(vec![
+                        fixed("q quit"),
+                        bound(Action::Drill, "open group"),
+                        fixed("backspace back"),
+                        bound(Action::Group, "group"),
+                        fixed("i profile"),
+                        fixed("esc close"),
+                    ]),
+                ),
+                None => format!(
+                    " {} ({})  row {}{of}  |  {}",
+                    column.map_or("", |c| c.name.as_str()),
+                    column.map_or("", |c| c.data_type.as_str()),
+                    row + 1,
+                    hints(vec![
+                        fixed("q quit"),
+                        fixed("enter detail"),
+                        bound(Action::Edit, "edit"),
+                        bound(Action::AddColumn, "add column"),
+                        bound(Action::Group, "group"),
+                        bound(Action::SaveView, "save view"),
+                        fixed("i profile"),
+                        fixed("F2 schema"),
+                        fixed("esc close"),
+                    ]),
+                ),
+            },
+        };
+        frame.render_widget(Paragraph::new(status_line).reversed(), status);
+    }
+}
+
+fn cell_text(array: &dyn Array, row: usize) -> String {
+    nested::summary(array, row).unwrap_or_else(|| {
+        ArrayFormatter::try_new(array, &FormatOptions::default())
+            .map(|formatter| formatter.value(row).to_string())
+            .unwrap_or_default()
+    })
+}
@@ -0,0 +1,572 @@
+use std::ops::Range;
+
+use datafusion::arrow::{array::Array, record_batch::RecordBatch};
+
+use crate::schema::ColumnInfo;
+
+pub(crate) const ROW_HEIGHT: f32 = 14.;
+pub(crate) const COL_WIDTH: f32 = 110.;
+pub(crate) const PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Hit {
+    Header(usize),
+    Cell { row: usize, col: usize },
+}
+
+// scroll position and layout of the grid in pixels, independent of how it gets drawn. Columns
+// are counted by their position in the view, the first `pinned` of them don't scroll sideways
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Viewport {
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+    pub(crate) header_height: f32,
+    pub(crate) ncols: usize,
+    pub(crate) pinned: usize,
+    pub(crate) row_height: f32,
+    pub(crate) col_width: f32,
+}
+
+impl Viewport {
+    pub(crate) fn new(width: f32, height: f32, header_height: f32, ncols: usize) -> Self {
+        Self {
+            x: 0.,
+            y: 0.,
+            width,
+            height,
+            header_height,
+            ncols,
+            pinned: 0,
+            row_height: ROW_HEIGHT,
+            col_width: COL_WIDTH,
+        }
+    }
+
+    // keeps the same row and column first
+    pub(crate) fn set_cell_size(&mut self, row_height: f32, col_width: f32) {
+        self.y = self.y / self.row_height * row_height;
+        self.x = self.x / self.col_width * col_width;
+        self.row_height = row_height;
+        self.col_width = col_width;
+    }
+
+    pub(crate) fn scroll(&mut self, x_delta: f32, y_delta: f32, speed: f32) {
+        self.y += speed * y_delta * y_delta * y_delta.signum();
+        self.x += speed * x_delta * x_delta * x_delta.signum();
+        self.y = self.y.min(0.);
+        self.x = self.x.min(0.);
+    }
+
+    pub(crate) fn scroll_to_row(&mut self, row: usize) {
+        self.y = -(row as f32 * self.row_height);
+    }
+
+    // brings the column right after the pinned ones
+    pub(crate) fn scroll_to_column(&mut self, col: usize) {
+        self.x = -(col.saturating_sub(self.pinned) as f32 * self.col_width);
+    }
+
+    pub(crate) fn resize(&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    pub(crate) fn first_row(&self) -> usize {
+        (-self.y / self.row_height) as usize
+    }
+
+    pub(crate) fn visible_rows(&self) -> Range<usize> {
+        let first = self.first_row();
+        let nrows = ((self.height - self.header_height).max(0.) / self.row_height).ceil() as usize;
+        first..first + nrows + 1
+    }
+
+    // the first of the columns scrolled sideways
+    pub(crate) fn first_column(&self) -> usize {
+        (-self.x / self.col_width) as usize + self.pinned
+    }
+
+    // the pinned columns and the scrolled ones right of them
+    pub(crate) fn visible_columns(&self) -> Vec<usize> {
+        let pinned = self.pinned.min(self.ncols);
+        let first = self.first_column().min(self.ncols);
+        let last = (((self.width - self.x) / self.col_width).ceil() as usize).min(self.ncols);
+        (0..pinned).chain(first..last.max(first)).collect()
+    }
+
+    pub(crate) fn cell_left(&self, col: usize) -> f32 {
+        match col < self.pinned {
+            true => col as f32 * self.col_width,
+            false => self.x + col as f32 * self.col_width,
+        }
+    }
+
+    // where the scrolled columns start, they are hidden left of it
+    pub(crate) fn pinned_width(&self) -> f32 {
+        self.pinned.min(self.ncols) as f32 * self.col_width
+    }
+
+    pub(crate) fn cell_top(&self, row: usize) -> f32 {
+        self.y + row as f32 * self.row_height + self.header_height
+    }
+
+    pub(crate) fn hit(&self, x: f32, y: f32) -> Option<Hit> {
+        let col = match x < self.pinned_width() {
+            true => (x / self.col_width) as usize,
+            false => ((x - self.x) / self.col_width) as usize,
+        };
+        if col >= self.ncols {
+            return None;
+        }
+        if y < self.header_height {
+            return Some(Hit::Header(col));
+        }
+        let row = ((y - self.y - self.header_height) / self.row_height) as usize;
+        Some(Hit::Cell { row, col })
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct DataWindow {
+    requested: usize,
+    // the requested page still has to be asked for
+    refetch: bool,
+    skip: usize,
+    batches: Vec<RecordBatch>,
+}
+
+impl DataWindow {
+    // a new page is fetched once the viewport drifted half a page from the last one
+    pub(crate) fn request(&mut self, viewport: &Viewport, page_size: usize) -> Option<usize> {
+        if std::mem::take(&mut self.refetch) {
+            return Some(self.requested);
+        }
+        let first = viewport.first_row();
+        if first.abs_diff(self.requested) > page_size / 2 {
+            self.requested = first;
+            return Some(first);
+        }
+        None
+    }
+
+    pub(crate) fn request_from(&mut self, row: usize) -> usize {
+        self.requested = row;
+        row
+    }
+
+    pub(crate) fn requested(&self) -> usize {
+        self.requested
+    }
+
+    pub(crate) fn refetch(&mut self) {
+        self.refetch = true;
+    }
+
+    pub(crate) fn load(&mut self, skip: usize, batches: Vec<RecordBatch>) {
+        self.skip = skip;
+        self.batches = batches;
+    }
+
+    pub(crate) fn page(&self) -> (usize, &[RecordBatch]) {
+        (self.skip, &self.batches)
+    }
+
+    pub(crate) fn value_at(&self, row: usize, col: usize) -> Option<(&dyn Array, usize)> {
+        let mut index = row.checked_sub(self.skip)?;
+        for batch in self.batches.iter() {
+            if index < batch.num_rows() {
+                let column = batch.columns().get(col)?;
+                return Some((column.as_ref(), index));
+            }
+            index -= batch.num_rows();
+        }
+        None
+    }
+}
+
+// which of the query's columns are shown and in what order, kept apart from the query so
+// hiding, moving or pinning a column needs nothing from the worker. Every column of a page is
+// fetched, so a column shown again is already there
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ColumnMap {
+    // the query column at each position of the view
+    shown: Vec<usize>,
+    // the position of each query column, none when hidden
+    positions: Vec<Option<usize>>,
+    // the first positions, which stay put while scrolling sideways
+    pinned: usize,
+}
+
+impl ColumnMap {
+    pub(crate) fn new(ncols: usize) -> Self {
+        let mut map = Self {
+            shown: (0..ncols).collect(),
+            ..Self::default()
+        };
+        map.index(ncols);
+        map
+    }
+
+    fn index(&mut self, ncols: usize) {
+        self.positions = vec![None; ncols];
+        for (position, &col) in self.shown.iter().enumerate() {
+            self.positions[col] = Some(position);
+        }
+        self.pinned = self.pinned.min(self.shown.len());
+    }
+
+    // the columns as a view file names them, others are shown after them. A name given twice
+    // is shown where it first is
+    pub(crate) fn restore(
+        columns: &[ColumnInfo],
+        order: &[String],
+        hidden: &[String],
+        pinned: usize,
+    ) -> Self {
+        let find = |name: &String| columns.iter().position(|c| c.name == *name);
+        let mut shown = vec![];
+        let mut pinned_shown = 0;
+        for (i, name) in order.iter().enumerate() {
+            let Some(col) = find(name).filter(|col| !shown.contains(col)) else {
+                continue;
+            };
+            shown.push(col);
+            if i < pinned {
+                pinned_shown += 1;
+            }
+        }
+        let pinned = pinned_shown;
+        let hidden: Vec<_> = hidden.iter().filter_map(find).collect();
+        let rest: Vec<_> = (0..columns.len())
+            .filter(|c| !shown.contains(c) && !hidden.contains(c))
+            .collect();
+        shown.extend(rest);
+        // something stays shown
+        if shown.is_empty() {
+            shown = (0..columns.len()).collect();
+        }
+        let mut map = Self {
+            shown,
+            pinned,
+            ..Self::default()
+        };
+        map.index(columns.len());
+        map
+    }
+
+    // the names of the shown columns in their order, and of the hidden ones
+    pub(crate) fn names(&self, columns: &[ColumnInfo]) -> (Vec<String>, Vec<String>) {
+        let name = |&col: &usize| columns[col].name.clone();
+        let hidden: Vec<_> = (0..self.positions.len())
+            .filter(|&c| self.position(c).is_none())
+            .collect();
+        (
+            self.shown.iter().map(name).collect(),
+            hidden.iter().map(name).collect(),
+        )
+    }
+
+    // columns are followed by name when the query changes, new ones are shown at the end
+    pub(crate) fn update(&mut self, old: &[ColumnInfo], new: &[ColumnInfo]) {
+        let find = |col: usize| new.iter().position(|c| c.name == old[col].name);
+        let pinned = self.shown[..self.pinned]
+            .iter()
+            .filter_map(|&c| find(c))
+            .count();
+        let mut shown: Vec<_> = self.shown.iter().filter_map(|&c| find(c)).collect();
+        let kept: Vec<_> = old.iter().map(|c| c.name.as_str()).collect();
+        shown.extend((0..new.len()).filter(|&c| !kept.contains(&new[c].name.as_str())));
+        self.shown = shown;
+        self.pinned = pinned;
+        self.index(new.len());
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.shown.len()
+    }
+
+    pub(crate) fn pinned(&self) -> usize {
+        self.pinned
+    }
+
+    pub(crate) fn column(&self, position: usize) -> Option<usize> {
+        self.shown.get(position).copied()
+    }
+
+    pub(crate) fn position(&self, col: usize) -> Option<usize> {
+        self.positions.get(col).copied().flatten()
+    }
+
+    // the last shown column stays
+    pub(crate) fn hide(&mut self, col: usize) -> bool {
+        let Some(position) = self.position(col).filter(|_| self.shown.len() > 1) else {
+            return false;
+        };
+        self.shown.remove(position);
+        if position < self.pinned {
+            self.pinned -= 1;
+        }
+        self.index(self.positions.len());
+        true
+    }
+
+    // a hidden column comes back at the end
+    pub(crate) fn show(&mut self, col: usize) -> bool {
+        if col >= self.positions.len() || self.position(col).is_some() {
+            return false;
+        }
+        self.shown.push(col);
+        self.index(self.positions.len());
+        true
+    }
+
+    pub(crate) fn show_all(&mut self) -> bool {
+        let hidden: Vec<_> = (0..self.positions.len())
+            .filter(|&c| self.position(c).is_none())
+            .collect();
+        for &col in &hidden {
+            self.show(col);
+        }
+        !hidden.is_empty()
+    }
+
+    // a column moved across the pinned ones is pinned or unpinned with them
+    pub(crate) fn move_to(&mut self, col: usize, to: usize) -> bool {
+        let Some(from) = self.position(col) else {
+            return false;
+        };
+        let to = to.min(self.shown.len() - 1);
+        if from == to {
+            return false;
+        }
+        self.shown.remove(from);
+        self.shown.insert(to, col);
+        if from < self.pinned && to >= self.pinned {
+            self.pinned -= 1;
+        } else if from >= self.pinned && to < self.pinned {
+            self.pinned += 1;
+        }
+        self.index(self.positions.len());
+        true
+    }
+
+    // pinning moves the column to the end of the pinned ones, unpinning to the start of the rest
+    pub(crate) fn toggle_pin(&mut self, col: usize) -> bool {
+        let Some(position) = self.position(col) else {
+            return false;
+        };
+        let pinned = position < self.pinned;
+        self.shown.remove(position);
+        match pinned {
+            true => {
+                self.pinned -= 1;
+                self.shown.insert(self.pinned, col);
+            }
+            false => {
+                self.shown.insert(self.pinned, col);
+                self.pinned += 1;
+            }
+        }
+        self.index(self.positions.len());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::arrow::{
+        array::{AsArray, Int32Array},
+        datatypes::Int32Type,
+    };
+
+    use super::*;
+
+    // ten columns, three of them across, and five rows under a 30 pixel header
+    fn viewport() -> Viewport {
+        Viewport::new(3. * COL_WIDTH, 30. + 5. * ROW_HEIGHT, 30., 10)
+    }
+
+    fn columns(names: &[&str]) -> Vec<ColumnInfo> {
+        names
+            .iter()
+            .map(|name| ColumnInfo {
+                name: name.to_string(),
+                data_type: "Int32".to_string(),
+                nullable: true,
+                metadata: vec![],
+                parquet: None,
+            })
+            .collect()
+    }
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    // the query columns in the order they are shown
+    fn shown(map: &ColumnMap) -> Vec<usize> {
+        (0..map.len()).filter_map(|p| map.column(p)).collect()
+    }
+
+    #[test]
+    fn scroll_stops_at_the_top_left() {
+        let mut viewport = viewport();
+        viewport.scroll(1., 1., 10.);
+        assert_eq!((viewport.x, viewport.y), (0., 0.));
+        viewport.scroll(-1., -2., 10.);
+        assert_eq!((viewport.x, viewport.y), (-10., -40.));
+        viewport.scroll(2., 3., 10.);
+        assert_eq!((viewport.x, viewport.y), (0., 0.));
+    }
+
+    #[test]
+    fn visible_rows_follow_the_scroll() {
+        let mut viewport = viewport();
+        assert_eq!(viewport.visible_rows(), 0..6);
+        viewport.scroll_to_row(3);
+        assert_eq!(viewport.first_row(), 3);
+        assert_eq!(viewport.visible_rows(), 3..9);
+    }
+
+    #[test]
+    fn pinned_columns_stay_visible() {
+        let mut viewport = viewport();
+        assert_eq!(viewport.visible_columns(), vec![0, 1, 2]);
+        viewport.pinned = 1;
+        viewport.scroll_to_column(4);
+        assert_eq!(viewport.first_column(), 4);
+        assert_eq!(viewport.visible_columns(), vec![0, 4, 5]);
+        assert_eq!(viewport.cell_left(0), 0.);
+        assert_eq!(viewport.cell_left(4), COL_WIDTH);
+        assert_eq!(viewport.pinned_width(), COL_WIDTH);
+    }
+
+    #[test]
+    fn hits_land_on_headers_cells_and_pinned_columns() {
+        let mut viewport = viewport();
+        viewport.pinned = 1;
+        viewport.scroll_to_column(4);
+        viewport.scroll_to_row(10);
+        assert_eq!(viewport.hit(50., 10.), Some(Hit::Header(0)));
+        assert_eq!(viewport.hit(150., 10.), Some(Hit::Header(4)));
+        let y = 30. + 2.5 * ROW_HEIGHT;
+        assert_eq!(viewport.hit(50., y), Some(Hit::Cell { row: 12, col: 0 }));
+        assert_eq!(viewport.hit(250., y), Some(Hit::Cell { row: 12, col: 5 }));
+        viewport.scroll_to_column(9);
+        assert_eq!(viewport.hit(250., y), None);
+    }
+
+    #[test]
+    fn pages_are_fetched_past_half_a_page() {
+        let half = PAGE_SIZE / 2;
+        let mut data = DataWindow::default();
+        let mut viewport = viewport();
+        assert_eq!(data.request(&viewport, PAGE_SIZE), None);
+        viewport.scroll_to_row(half);
+        assert_eq!(data.request(&viewport, PAGE_SIZE), None);
+        viewport.scroll_to_row(half + 1);
+        assert_eq!(data.request(&viewport, PAGE_SIZE), Some(half + 1));
+        assert_eq!(data.request(&viewport, PAGE_SIZE), None);
+        data.refetch();
+        assert_eq!(data.request(&viewport, PAGE_SIZE), Some(half + 1));
+    }
+
+    #[test]
+    fn values_are_found_across_batches() {
+        let batch = |values: Vec<i32>| {
+            let array = Arc::new(Int32Array::from(values)) as _;
+            RecordBatch::try_from_iter([("a", array)]).unwrap()
+        };
+        let mut data = DataWindow::default();
+        data.load(10, vec![batch(vec![1, 2]), batch(vec![3, 4, 5])]);
+        let value = |row| {
+            let (array, index) = data.value_at(row, 0)?;
+            Some(array.as_primitive::<Int32Type>().value(index))
+        };
+        assert_eq!(value(9), None);
+        assert_eq!(value(10), Some(1));
+        assert_eq!(value(12), Some(3));
+        assert_eq!(value(14), Some(5));
+        assert_eq!(value(15), None);
+        assert!(data.value_at(10, 1).is_none());
+    }
+
+    #[test]
+    fn hiding_keeps_the_last_column() {
+        let mut map = ColumnMap::new(3);
+        assert!(map.hide(1));
+        assert_eq!(shown(&map), vec![0, 2]);
+        assert_eq!(map.position(1), None);
+        assert_eq!(map.position(2), Some(1));
+        assert!(!map.hide(1));
+        assert!(map.hide(0));
+        assert!(!map.hide(2));
+        assert!(map.show(1));
+        assert_eq!(shown(&map), vec![2, 1]);
+        assert!(map.show_all());
+        assert_eq!(shown(&map), vec![2, 1, 0]);
+        assert!(!map.show_all());
+    }
+
+    #[test]
+    fn moving_across_the_pinned_columns_pins_them() {
+        let mut map = ColumnMap::new(4);
+        assert!(map.toggle_pin(2));
+        assert_eq!((shown(&map), map.pinned()), (vec![2, 0, 1, 3], 1));
+        assert!(map.move_to(3, 0));
+        assert_eq!((shown(&map), map.pinned()), (vec![3, 2, 0, 1], 2));
+        assert!(map.move_to(2, 3));
+        assert_eq!((shown(&map), map.pinned()), (vec![3, 0, 1, 2], 1));
+        assert!(!map.move_to(2, 10));
+        assert!(map.toggle_pin(3));
+        assert_eq!((shown(&map), map.pinned()), (vec![3, 0, 1, 2], 0));
+    }
+
+    #[test]
+    fn hiding_a_pinned_column_unpins_it() {
+        let mut map = ColumnMap::new(3);
+        map.toggle_pin(0);
+        map.toggle_pin(1);
+        assert!(map.hide(0));
+        assert_eq!((shown(&map), map.pinned()), (vec![1, 2], 1));
+    }
+
+    #[test]
+    fn restoring_follows_the_names() {
+        let columns = columns(&["a", "b", "c", "d"]);
+        let order = strings(&["c", "gone", "a", "c", "b"]);
+        let map = ColumnMap::restore(&columns, &order, &strings(&["d"]), 3);
+        assert_eq!(shown(&map), vec![2, 0, 1]);
+        assert_eq!(map.pinned(), 2);
+        assert_eq!(map.position(3), None);
+        let (order, hidden) = map.names(&columns);
+        assert_eq!(
+            (order, hidden),
+            (strings(&["c", "a", "b"]), strings(&["d"]))
+        );
+        // new columns are shown at the end
+        let map = ColumnMap::restore(&columns, &strings(&["b"]), &[], 0);
+        assert_eq!(shown(&map), vec![1, 0, 2, 3]);
+    }
+
+    #[test]
+    fn updates_follow_columns_by_name() {
+        let old = columns(&["a", "b", "c"]);
+        let mut map = ColumnMap::new(3);
+        map.toggle_pin(2);
+        map.hide(1);
+        let new = columns(&["c", "a", "x"]);
+        map.update(&old, &new);
+        assert_eq!(shown(&map), vec![0, 1, 2]);
+        assert_eq!(map.pinned(), 1);
+        let new = columns(&["a", "b", "c"]);
+        let mut map = ColumnMap::new(3);
+        map.hide(1);
+        map.update(&old, &new);
+        assert_eq!(map.position(1), None);
+    }
+}
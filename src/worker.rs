@@ -0,0 +1,802 @@
+use std::{fs, sync::Arc, time::Instant};
+
+use datafusion::{
+    arrow::{
+        datatypes::{DataType, FieldRef},
+        record_batch::RecordBatch,
+    },
+    error::Result,
+    prelude::*,
+};
+use tokio::{
+    runtime::Builder,
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+
+use crate::{
+    cast,
+    computed::{self, Computed},
+    config,
+    edit::{self, Editor, Edits, RowKey, Target},
+    group::{self, Grouping},
+    histogram::Distribution,
+    nested,
+    pipe::{self, Progress},
+    profile::Profile,
+    reload,
+    schema::{self, ColumnInfo},
+    session::Setup,
+    source::{self, FileOptions},
+};
+
+pub(crate) enum Request {
+    Rows(usize),
+    Profile(usize),
+    Promote(usize),
+    Cast(usize, Option<DataType>),
+    // the text typed into a cell, by row and column
+    Edit(usize, usize, String),
+    Undo,
+    Redo,
+    Save,
+    ExportSql,
+    // a column computed from a sql expression, optionally ending in `as name`
+    AddColumn(String),
+    // aggregates by some columns, as in `sum(amount) by region`
+    Group(String),
+    // the rows of the group in a row of the grouped table
+    Drill(usize),
+    // from a group's rows to the groups, and from the groups to the table
+    Back,
+    // what the table is set up with, to save the view
+    Setup,
+}
+
+pub(crate) enum Response {
+    Rows(usize, Vec<RecordBatch>),
+    Columns(Vec<ColumnInfo>),
+    Profile(Profile),
+    // the column whose profile couldn't be computed, and why
+    ProfileFailed(usize, String),
+    Distribution(usize, Option<Distribution>),
+    Cast { column: usize, failed: usize },
+    Progress(Progress),
+    // the file was rewritten, with what changed about its columns
+    Reloaded(Vec<String>),
+    // the cells that hold an edit, after each change to them
+    Edited(Vec<(usize, usize)>),
+    // the text comes back to be fixed
+    EditFailed(Editor),
+    // what came of saving or exporting the edits
+    Message(String),
+    // what the tab shows instead of the table's rows, if anything
+    Viewing(Option<String>),
+    Setup(Setup),
+}
+
+// the first columns, and what the tab shows if it opens grouped, or why the file can't be read
+pub(crate) type Opened = Result<(Vec<ColumnInfo>, Option<String>), String>;
+
+// wakes up the front end, if it wants to be, to draw what the worker sent
+#[derive(Clone, Default)]
+pub(crate) struct Notify(Option<Arc<dyn Fn() + Send + Sync>>);
+
+impl Notify {
+    pub(crate) fn new(f: impl Fn() + Send + Sync + 'static) -> Self {
+        Self(Some(Arc::new(f)))
+    }
+
+    pub(crate) fn notify(&self) {
+        if let Some(f) = &self.0 {
+            f();
+        }
+    }
+}
+
+pub(crate) fn spawn_worker(
+    ctx: SessionContext,
+    table: String,
+    path: String,
+    options: FileOptions,
+    setup: Option<Setup>,
+    notify: Notify,
+) -> (
+    mpsc::Sender<Request>,
+    mpsc::Receiver<Response>,
+    oneshot::Receiver<Opened>,
+) {
+    let (query_tx, mut query_rx) = mpsc::channel::<Request>(2);
+    let (results_tx, results_rx) = mpsc::channel::<Response>(2);
+    let (columns_tx, columns_rx) = oneshot::channel();
+
+    let rt = Builder::new_current_thread().enable_all().build().unwrap();
+    std::thread::spawn(move || {
+        rt.block_on(async move {
+            // a saved view brings its own column types and computed columns
+            let setup = setup.unwrap_or_else(|| Setup {
+                casts: cast::load(&path),
+                computed: computed::load(&path),
+                ..Setup::default()
+            });
+            let mut casts = setup.casts;
+            let types = cast::columns(&casts);
+            let registered = source::register(&ctx, &table, &path, &options, &types);
+            let opened = match registered.await {
+                Ok(()) => ctx.table(&table).await,
+                Err(e) => Err(e),
+            };
+            let mut table_df = match opened {
+                Ok(table_df) => table_df,
+                Err(e) => {
+                    columns_tx.send(Err(format!("could not open {path}: {e}"))).ok();
+                    notify.notify();
+                    return;
+                }
+            };
+            let mut promoted = setup.promoted;
+            let mut computed = setup.computed;
+            let mut df = match build(&ctx, &table_df, &promoted, &computed, &casts) {
+                Ok(df) => df,
+                Err(e) => {
+                    log::error!("could not apply saved column types and computed columns: {e}");
+                    casts.clear();
+                    computed.clear();
+                    promoted.clear();
+                    table_df.clone()
+                }
+            };
+            let mut grouping: Option<Grouping> = None;
+            let mut drilled: Option<(usize, Expr, String)> = None;
+            if let Some(text) = &setup.grouping {
+                match regroup(&df, text, setup.drilled).await {
+                    Ok((parsed, opened, shown)) => {
+                        grouping = Some(parsed);
+                        drilled = opened;
+                        df = shown;
+                    }
+                    Err(e) => log::error!("could not group the rows by {text}: {e}"),
+                }
+            }
+            let columns = schema::column_infos(&df.schema().into(), &path);
+            columns_tx.send(Ok((columns, viewing(&grouping, &drilled)))).ok();
+            notify.notify();
+            let mut plots = Plots::default();
+            plots.spawn(&df, &results_tx, &notify);
+
+            let mut progress = pipe::progress(&path);
+            // kept across requests, a new wait per request would never see a busy table's file change
+            let changed = reload::changed(reload::watch(&path, &options));
+            tokio::pin!(changed);
+            let mut edits = Edits::default();
+            // the last page sent and its number of rows
+            let mut page = (0, 0);
+            loop {
+                let request = tokio::select! {
+                    request = query_rx.recv() => match request {
+                        Some(request) => request,
+                        None => break,
+                    },
+                    grown = pipe::grown(&mut progress) => {
+                        // a short page can take some of the new rows
+                        if page.1 < config::get().page_size {
+                            page = send_rows(&df, page.0, overlay(&grouping, &edits), &results_tx).await;
+                        }
+                        results_tx.send(Response::Progress(grown)).await.unwrap();
+                        if grown.done {
+                            plots.spawn(&df, &results_tx, &notify);
+                        }
+                        notify.notify();
+                        continue;
+                    }
+                    watched = &mut changed => {
+                        changed.set(reload::changed(Some(watched)));
+                        let reloaded =
+                            reload(&ctx, &table, &path, &options, &promoted, &computed, &casts);
+                        let (reloaded, built) = match reloaded.await {
+                            Ok(built) => built,
+                            Err(e) => {
+                                log::error!("could not reload {path}: {e}");
+                                continue;
+                            }
+                        };
+                        let built = match shown(built.clone(), &grouping, &drilled).await {
+                            Ok(shown) => shown,
+                            Err(e) => {
+                                log::error!("could not group the reloaded table: {e}");
+                                grouping = None;
+                                drilled = None;
+                                send_viewing(&grouping, &drilled, &results_tx).await;
+                                built
+                            }
+                        };
+                        // the view stays where it was as long as the columns do
+                        let mut changes = schema::changes(&df.schema().into(), &built.schema().into());
+                        let same_columns = changes.is_empty();
+                        table_df = reloaded;
+                        df = built;
+                        // edits are kept by row number, which may be another row now
+                        if !edits.is_empty() {
+                            edits = Edits::default();
+                            changes.push("edits were dropped, the rows may have moved".to_string());
+                        }
+                        if same_columns {
+                            page = send_rows(&df, page.0, overlay(&grouping, &edits), &results_tx).await;
+                            results_tx.send(Response::Edited(vec![])).await.unwrap();
+                            plots.spawn(&df, &results_tx, &notify);
+                        } else {
+                            send_columns(&df, &path, overlay(&grouping, &edits), &mut plots, &results_tx, &notify).await;
+                        }
+                        results_tx.send(Response::Reloaded(changes)).await.unwrap();
+                        notify.notify();
+                        continue;
+                    }
+                };
+                // changes to the table are made to all of its rows, not to groups
+                let changes_table = matches!(
+                    request,
+                    Request::Promote(_)
+                        | Request::Cast(..)
+                        | Request::Edit(..)
+                        | Request::Undo
+                        | Request::Redo
+                        | Request::Save
+                        | Request::ExportSql
+                        | Request::AddColumn(_)
+                );
+                if changes_table && grouping.is_some() {
+                    let message = "go back to the table to change it".to_string();
+                    results_tx.send(Response::Message(message)).await.unwrap();
+                    notify.notify();
+                    continue;
+                }
+                match request {
+                    Request::Rows(skip) => {
+                        let now = Instant::now();
+                        page = send_rows(&df, skip, overlay(&grouping, &edits), &results_tx).await;
+                        notify.notify();
+                        log::debug!("query done in: {:?}", now.elapsed());
+                    }
+                    Request::Profile(column) => {
+                        // aggregates scan the whole file, don't hold up paging
+                        let df = df.clone();
+                        let results_tx = results_tx.clone();
+                        let notify = notify.clone();
+                        tokio::spawn(async move {
+                            let now = Instant::now();
+                            let response = match Profile::compute(df, column).await {
+                                Ok(profile) => Response::Profile(profile),
+                                Err(e) => Response::ProfileFailed(column, e.to_string()),
+                            };
+                            results_tx.send(response).await.unwrap();
+                            notify.notify();
+                            log::debug!("profile done in: {:?}", now.elapsed());
+                        });
+                    }
+                    Request::Promote(column) => {
+                        let Some(field) = field(&df, column, &results_tx, &notify).await else {
+                            continue;
+                        };
+                        promoted.push(field.name().clone());
+                        match build(&ctx, &table_df, &promoted, &computed, &casts) {
+                            Ok(built) => df = built,
+                            Err(e) => {
+                                log::error!("could not promote fields: {e}");
+                                promoted.pop();
+                                continue;
+                            }
+                        }
+                        send_columns(&df, &path, overlay(&grouping, &edits), &mut plots, &results_tx, &notify).await;
+                    }
+                    Request::Cast(column, data_type) => {
+                        let Some(field) = field(&df, column, &results_tx, &notify).await else {
+                            continue;
+                        };
+                        let name = field.name().clone();
+                        // put back if the file can't be read with the new type
+                        let before = casts.clone();
+                        casts.retain(|(n, _)| *n != name);
+                        let others = casts.clone();
+                        if let Some(data_type) = &data_type {
+                            casts.push((name.clone(), data_type.clone()));
+                        }
+                        let recast = recast(
+                            &ctx,
+                            &table,
+                            &path,
+                            &options,
+                            &promoted,
+                            &computed,
+                            &casts,
+                            &others,
+                            (&name, &data_type),
+                        );
+                        let (registered, failed, built) = match recast.await {
+                            Ok(cast) => cast,
+                            Err(e) => {
+                                casts = before;
+                                let types = cast::columns(&casts);
+                                let restored =
+                                    source::register(&ctx, &table, &path, &options, &types).await;
+                                if let Err(e) = restored {
+                                    log::error!("could not read {path} with the types before: {e}");
+                                }
+                                let message = format!("could not change the type of {name}: {e}");
+                                results_tx.send(Response::Message(message)).await.unwrap();
+                                notify.notify();
+                                continue;
+                            }
+                        };
+                        table_df = registered;
+                        df = built;
+                        cast::save(&path, &casts);
+                        send_columns(&df, &path, overlay(&grouping, &edits), &mut plots, &results_tx, &notify).await;
+                        results_tx
+                            .send(Response::Cast { column, failed })
+                            .await
+                            .unwrap();
+                        notify.notify();
+                    }
+                    Request::Edit(row, column, text) => {
+                        let Some(field) = field(&df, column, &results_tx, &notify).await else {
+                            continue;
+                        };
+                        // computed and promoted columns aren't in the table to be updated
+                        let parsed = match edit::column_index(&table_df, field.name()) {
+                            Some(_) => edit::parse(&text, &field),
+                            None => Err(format!("{} is not a column of the table", field.name())),
+                        };
+                        match parsed {
+                            Ok(value) => edits.push(row, field.name().clone(), value),
+                            Err(error) => {
+                                let editor = Editor {
+                                    target: Target::Cell { row, col: column },
+                                    text,
+                                    error: Some(error),
+                                };
+                                results_tx
+                                    .send(Response::EditFailed(editor))
+                                    .await
+                                    .unwrap();
+                                notify.notify();
+                                continue;
+                            }
+                        }
+                        page = send_rows(&df, page.0, overlay(&grouping, &edits), &results_tx).await;
+                        results_tx
+                            .send(Response::Edited(edits.cells(&df)))
+                            .await
+                            .unwrap();
+                        notify.notify();
+                    }
+                    Request::Undo | Request::Redo => {
+                        let changed = match request {
+                            Request::Undo => edits.undo(),
+                            _ => edits.redo(),
+                        };
+                        if !changed {
+                            continue;
+                        }
+                        page = send_rows(&df, page.0, overlay(&grouping, &edits), &results_tx).await;
+                        results_tx
+                            .send(Response::Edited(edits.cells(&df)))
+                            .await
+                            .unwrap();
+                        notify.notify();
+                    }
+                    Request::AddColumn(text) => {
+                        let column = computed::parse(&text);
+                        if let Err(e) = computed::check(&ctx.state(), &df, &column) {
+                            let editor = Editor {
+                                target: Target::Column,
+                                text,
+                                error: Some(e.to_string()),
+                            };
+                            results_tx
+                                .send(Response::EditFailed(editor))
+                                .await
+                                .unwrap();
+                            notify.notify();
+                            continue;
+                        }
+                        computed.push(column);
+                        match build(&ctx, &table_df, &promoted, &computed, &casts) {
+                            Ok(built) => df = built,
+                            Err(e) => {
+                                log::error!("could not add the column: {e}");
+                                computed.pop();
+                                continue;
+                            }
+                        }
+                        computed::save(&path, &computed);
+                        send_columns(&df, &path, overlay(&grouping, &edits), &mut plots, &results_tx, &notify).await;
+                    }
+                    Request::Group(text) => {
+                        let base = build(&ctx, &table_df, &promoted, &computed, &casts);
+                        let grouped = match group::parse(&text) {
+                            Ok(parsed) => match base {
+                                Ok(base) => parsed
+                                    .apply(&base)
+                                    .await
+                                    .map(|grouped| (parsed, grouped))
+                                    .map_err(|e| e.to_string()),
+                                Err(e) => Err(e.to_string()),
+                            },
+                            Err(e) => Err(e),
+                        };
+                        match grouped {
+                            Ok((parsed, grouped)) => {
+                                grouping = Some(parsed);
+                                drilled = None;
+                                df = grouped;
+                            }
+                            Err(error) => {
+                                let editor = Editor {
+                                    target: Target::Group,
+                                    text,
+                                    error: Some(error),
+                                };
+                                results_tx
+                                    .send(Response::EditFailed(editor))
+                                    .await
+                                    .unwrap();
+                                notify.notify();
+                                continue;
+                            }
+                        }
+                        send_viewing(&grouping, &drilled, &results_tx).await;
+                        send_columns(&df, &path, None, &mut plots, &results_tx, &notify).await;
+                    }
+                    Request::Drill(row) => {
+                        let Some(grouped) = grouping.as_ref().filter(|_| drilled.is_none()) else {
+                            continue;
+                        };
+                        let filter = grouped.filter(&df, row).await;
+                        let base = build(&ctx, &table_df, &promoted, &computed, &casts);
+                        let rows = match (filter, base) {
+                            (Ok((filter, values)), Ok(base)) => base
+                                .filter(filter.clone())
+                                .map(|rows| ((row, filter, values), rows)),
+                            (Err(e), _) | (_, Err(e)) => Err(e),
+                        };
+                        match rows {
+                            Ok((opened, rows)) => {
+                                drilled = Some(opened);
+                                df = rows;
+                            }
+                            Err(e) => {
+                                log::error!("could not open the group's rows: {e}");
+                                continue;
+                            }
+                        }
+                        send_viewing(&grouping, &drilled, &results_tx).await;
+                        send_columns(&df, &path, None, &mut plots, &results_tx, &notify).await;
+                    }
+                    Request::Back => {
+                        if grouping.is_none() {
+                            continue;
+                        }
+                        if drilled.take().is_none() {
+                            grouping = None;
+                        }
+                        let base = build(&ctx, &table_df, &promoted, &computed, &casts);
+                        let back = match base {
+                            Ok(base) => shown(base, &grouping, &drilled).await,
+                            Err(e) => Err(e),
+                        };
+                        match back {
+                            Ok(back) => df = back,
+                            Err(e) => {
+                                log::error!("could not go back: {e}");
+                                continue;
+                            }
+                        }
+                        send_viewing(&grouping, &drilled, &results_tx).await;
+                        send_columns(
+                            &df,
+                            &path,
+                            overlay(&grouping, &edits),
+                            &mut plots,
+                            &results_tx,
+                            &notify,
+                        )
+                        .await;
+                    }
+                    Request::Setup => {
+                        let setup = Setup {
+                            casts: casts.clone(),
+                            computed: computed.clone(),
+                            promoted: promoted.clone(),
+                            grouping: grouping.as_ref().map(Grouping::describe),
+                            drilled: drilled.as_ref().map(|(row, ..)| *row),
+                        };
+                        results_tx.send(Response::Setup(setup)).await.unwrap();
+                        notify.notify();
+                    }
+                    Request::Save => {
+                        // writing every row shouldn't hold up paging. The table has the rows of the
+                        // ungrouped view in the same order, without the columns the view adds
+                        let (table_df, edits) = (table_df.clone(), edits.clone());
+                        let (path, options) = (path.clone(), options.clone());
+                        let results_tx = results_tx.clone();
+                        let notify = notify.clone();
+                        tokio::spawn(async move {
+                            let message = match edits.save(table_df, &path, &options).await {
+                                Ok(output) => format!("saved to {}", output.display()),
+                                Err(e) => format!("could not save: {e}"),
+                            };
+                            results_tx.send(Response::Message(message)).await.unwrap();
+                            notify.notify();
+                        });
+                    }
+                    Request::ExportSql => {
+                        let message = if edits.is_empty() {
+                            "there are no edits to export".to_string()
+                        } else {
+                            let output = edit::sql_path(&path, &table);
+                            let exported = match RowKey::of(&path).await {
+                                Ok(key) => edits.sql(&table_df, &table, &key).await,
+                                Err(e) => Err(e),
+                            };
+                            let written = match exported {
+                                Ok((sql, skipped)) => fs::write(&output, sql)
+                                    .map(|()| skipped)
+                                    .map_err(|e| e.to_string()),
+                                Err(e) => Err(e.to_string()),
+                            };
+                            match written {
+                                Ok(skipped) if skipped.is_empty() => {
+                                    format!("exported the edits to {}", output.display())
+                                }
+                                Ok(skipped) => format!(
+                                    "exported the edits to {}, left out {}",
+                                    output.display(),
+                                    skipped.join("; ")
+                                ),
+                                Err(e) => format!("could not export the edits: {e}"),
+                            }
+                        };
+                        results_tx.send(Response::Message(message)).await.unwrap();
+                        notify.notify();
+                    }
+                }
+            }
+        })
+    });
+    (query_tx, results_rx, columns_rx)
+}
+
+// the field a request was made for, unless the columns changed while it waited, which is
+// told about
+async fn field(
+    df: &DataFrame,
+    column: usize,
+    results_tx: &mpsc::Sender<Response>,
+    notify: &Notify,
+) -> Option<FieldRef> {
+    let field = df.schema().fields().get(column).map(|f| f.field().clone());
+    if field.is_none() {
+        let message = format!("column {} is gone, the columns have changed", column + 1);
+        results_tx.send(Response::Message(message)).await.unwrap();
+        notify.notify();
+    }
+    field
+}
+
+// a page that can't be read is told about, and counts as empty
+async fn send_rows(
+    df: &DataFrame,
+    skip: usize,
+    edits: Option<&Edits>,
+    results_tx: &mpsc::Sender<Response>,
+) -> (usize, usize) {
+    let batches = match read_page(df, skip, edits).await {
+        Ok(batches) => batches,
+        Err(e) => {
+            let message = format!("could not read the rows from {}: {e}", skip + 1);
+            results_tx.send(Response::Message(message)).await.unwrap();
+            return (skip, 0);
+        }
+    };
+    let nrows = batches.iter().map(|b| b.num_rows()).sum();
+    results_tx
+        .send(Response::Rows(skip, batches))
+        .await
+        .unwrap();
+    (skip, nrows)
+}
+
+async fn read_page(df: &DataFrame, skip: usize, edits: Option<&Edits>) -> Result<Vec<RecordBatch>> {
+    let batches = df
+        .clone()
+        .limit(skip, Some(config::get().page_size))?
+        .collect()
+        .await?;
+    let mut offset = skip;
+    batches
+        .into_iter()
+        .map(|batch| {
+            let rows = batch.num_rows();
+            offset += rows;
+            match edits {
+                Some(edits) => edits.apply(batch, offset - rows),
+                None => Ok(batch),
+            }
+        })
+        .collect()
+}
+
+// struct promotions, type overrides and computed columns are replayed on the table, so a cast
+// always starts from the values in the file
+fn build(
+    ctx: &SessionContext,
+    table_df: &DataFrame,
+    promoted: &[String],
+    computed: &[Computed],
+    casts: &[(String, DataType)],
+) -> Result<DataFrame> {
+    let mut df = table_df.clone();
+    for name in promoted {
+        let Some(column) = df.schema().fields().iter().position(|f| f.name() == name) else {
+            continue;
+        };
+        df = nested::promote_fields(df, column)?;
+    }
+    let df = cast::apply(df, casts)?;
+    computed::apply(&ctx.state(), df, computed, casts)
+}
+
+// the table read again from the file, with everything the view adds to it
+async fn reload(
+    ctx: &SessionContext,
+    table: &str,
+    path: &str,
+    options: &FileOptions,
+    promoted: &[String],
+    computed: &[Computed],
+    casts: &[(String, DataType)],
+) -> Result<(DataFrame, DataFrame)> {
+    source::register(ctx, table, path, options, &cast::columns(casts)).await?;
+    let table_df = ctx.table(table).await?;
+    let df = build(ctx, &table_df, promoted, computed, casts)?;
+    Ok((table_df, df))
+}
+
+// the table read with a column's new type, how many of its values don't take the type, and
+// the view on top
+#[allow(clippy::too_many_arguments)]
+async fn recast(
+    ctx: &SessionContext,
+    table: &str,
+    path: &str,
+    options: &FileOptions,
+    promoted: &[String],
+    computed: &[Computed],
+    casts: &[(String, DataType)],
+    others: &[(String, DataType)],
+    (name, data_type): (&str, &Option<DataType>),
+) -> Result<(DataFrame, usize, DataFrame)> {
+    source::register(ctx, table, path, options, &cast::columns(casts)).await?;
+    let table_df = ctx.table(table).await?;
+    let failed = match data_type {
+        Some(data_type) => {
+            let uncast = build(ctx, &table_df, promoted, computed, others)?;
+            cast::failures(uncast, name, data_type).await?
+        }
+        None => 0,
+    };
+    let df = build(ctx, &table_df, promoted, computed, casts)?;
+    Ok((table_df, failed, df))
+}
+
+// edits are made to rows of the table, grouped rows show none of them
+fn overlay<'a>(grouping: &Option<Grouping>, edits: &'a Edits) -> Option<&'a Edits> {
+    grouping.is_none().then_some(edits)
+}
+
+// what the tab shows of the view: every row, a row per group, or the rows of one group
+async fn shown(
+    base: DataFrame,
+    grouping: &Option<Grouping>,
+    drilled: &Option<(usize, Expr, String)>,
+) -> Result<DataFrame> {
+    match (grouping, drilled) {
+        (_, Some((_, filter, _))) => base.filter(filter.clone()),
+        (Some(grouping), None) => grouping.apply(&base).await,
+        (None, None) => Ok(base),
+    }
+}
+
+// a grouping as saved in a view, and the group that was open in it
+async fn regroup(
+    base: &DataFrame,
+    text: &str,
+    drilled: Option<usize>,
+) -> Result<(Grouping, Option<(usize, Expr, String)>, DataFrame), String> {
+    let grouping = group::parse(text)?;
+    let grouped = grouping.apply(base).await.map_err(|e| e.to_string())?;
+    let Some(row) = drilled else {
+        return Ok((grouping, None, grouped));
+    };
+    let (filter, values) = grouping
+        .filter(&grouped, row)
+        .await
+        .map_err(|e| e.to_string())?;
+    let rows = base
+        .clone()
+        .filter(filter.clone())
+        .map_err(|e| e.to_string())?;
+    Ok((grouping, Some((row, filter, values)), rows))
+}
+
+fn viewing(grouping: &Option<Grouping>, drilled: &Option<(usize, Expr, String)>) -> Option<String> {
+    match (grouping, drilled) {
+        (_, Some((_, _, values))) => Some(values.clone()),
+        (Some(grouping), None) => Some(grouping.describe()),
+        (None, None) => None,
+    }
+}
+
+async fn send_viewing(
+    grouping: &Option<Grouping>,
+    drilled: &Option<(usize, Expr, String)>,
+    results_tx: &mpsc::Sender<Response>,
+) {
+    let viewing = viewing(grouping, drilled);
+    results_tx.send(Response::Viewing(viewing)).await.unwrap();
+}
+
+async fn send_columns(
+    df: &DataFrame,
+    path: &str,
+    edits: Option<&Edits>,
+    plots: &mut Plots,
+    results_tx: &mpsc::Sender<Response>,
+    notify: &Notify,
+) {
+    // stopped before the columns go out, so none of its plots come after them
+    plots.abort();
+    let columns = schema::column_infos(&df.schema().into(), path);
+    results_tx.send(Response::Columns(columns)).await.unwrap();
+    // the edited cells move with their columns
+    results_tx
+        .send(Response::Edited(edits.map_or(vec![], |e| e.cells(df))))
+        .await
+        .unwrap();
+    notify.notify();
+    plots.spawn(df, results_tx, notify);
+}
+
+// the distribution plots being computed for the columns last sent
+#[derive(Default)]
+struct Plots(Option<JoinHandle<()>>);
+
+impl Plots {
+    // plots of the columns before are dropped, they would land on whatever column took
+    // their place
+    fn spawn(&mut self, df: &DataFrame, results_tx: &mpsc::Sender<Response>, notify: &Notify) {
+        self.abort();
+        let (df, results_tx, notify) = (df.clone(), results_tx.clone(), notify.clone());
+        self.0 = Some(tokio::spawn(async move {
+            for column in 0..df.schema().fields().len() {
+                let distribution = Distribution::compute(df.clone(), column)
+                    .await
+                    .unwrap_or_else(|e| {
+                        log::error!("histogram failed for column {column}: {e}");
+                        None
+                    });
+                results_tx
+                    .send(Response::Distribution(column, distribution))
+                    .await
+                    .unwrap();
+                notify.notify();
+            }
+        }));
+    }
+
+    fn abort(&mut self) {
+        if let Some(task) = self.0.take() {
+            task.abort();
+        }
+    }
+}